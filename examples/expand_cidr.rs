@@ -0,0 +1,19 @@
+// 展开CIDR、IP和域名：CIDR随机生成指定数量不重复的IP，IP和域名原样返回
+// cargo run --example expand_cidr -- 50 192.168.1.0/24 2001:db8::/120 github.com
+use curl_cdn_cgi_trace_rust::utils::network::expand_lines;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let count: usize = args
+        .next()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(50);
+    let mut lines: Vec<String> = args.collect();
+    if lines.is_empty() {
+        lines = vec!["192.168.1.0/24".to_string(), "github.com".to_string()];
+    }
+    for (line, ips) in expand_lines(lines, 4, count, rand::random()) {
+        println!("{}: {:?}", line, ips);
+        println!("Total: {}", ips.len());
+    }
+}
@@ -0,0 +1,19 @@
+// 在locations.json中查找数据中心，文件不存在时会先下载
+// cargo run --example lookup_colo -- HRE
+use curl_cdn_cgi_trace_rust::utils::locations::load_airports;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let target_iata = std::env::args().nth(1).unwrap_or_else(|| "HRE".to_string());
+    let airports = load_airports(false).await?;
+
+    if let Some(airport) = airports.iter().find(|a| a.iata == target_iata) {
+        println!("CCA2: {}", airport.cca2);
+        println!("Region: {}", airport.region);
+        println!("City: {}", airport.city);
+    } else {
+        println!("Airport with IATA code '{}' not found", target_iata);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,15 @@
+// 在CIDR的范围内随机生成IP地址，相同的种子生成相同的IP
+// cargo run --example random_ip -- 104.17.17.0/24 42
+use curl_cdn_cgi_trace_rust::utils::network::process_ip_cidr_hosts;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let cidr = args.next().unwrap_or_else(|| "104.17.17.0/24".to_string());
+    let seed = args
+        .next()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(rand::random);
+    for ip in process_ip_cidr_hosts(vec![cidr], 1, 1, seed) {
+        println!("随机生成的IP地址: {}", ip);
+    }
+}
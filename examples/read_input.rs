@@ -0,0 +1,15 @@
+// 读取输入文件：去掉空行和重复的行，排序后返回
+// cargo run --example read_input -- ips-v4.txt
+use curl_cdn_cgi_trace_rust::utils::files::read_text_file;
+
+fn main() {
+    let filename = std::env::args().nth(1).unwrap_or_else(|| "ips-v4.txt".to_string());
+    match read_text_file(&filename) {
+        Ok(lines) => {
+            println!("{:?}", lines);
+        }
+        Err(error) => {
+            eprintln!("Error reading file: {:?}", error);
+        }
+    }
+}
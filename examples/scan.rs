@@ -0,0 +1,31 @@
+// 使用Scanner扫描几个地址，逐条打印结果，同时写入output.jsonl
+// cargo run --example scan -- 104.16.0.0/24 1.1.1.1
+use curl_cdn_cgi_trace_rust::{ utils, Scanner, Target };
+use utils::files::{ JsonSink, WriteMode };
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut lines: Vec<String> = std::env::args().skip(1).collect();
+    if lines.is_empty() {
+        lines = vec!["104.16.0.0/24".to_string()];
+    }
+    let targets: Vec<Target> = utils::network
+        ::process_ip_cidr_hosts(lines, 4, 3, rand::random())
+        .into_iter()
+        .map(Target::from)
+        .collect();
+
+    let mut scan = Scanner::new(targets)
+        .pool(10)
+        .airports(utils::locations::load_airports(false).await?)
+        .sink(Box::new(JsonSink::new("output.jsonl", Vec::new(), WriteMode::Overwrite, true)))
+        .scan()?;
+    for (_, result) in scan.by_ref() {
+        match result {
+            Ok(record) => println!("{:<40} {:<4} {:>6?} {}", record.ip, record.colo, record.delay, record.cdn),
+            Err(reason) => eprintln!("{}", reason),
+        }
+    }
+    scan.finish()?;
+    Ok(())
+}
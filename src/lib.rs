@@ -0,0 +1,36 @@
+//! 批量扫描地址是否走CloudFlare CDN的流量，也可以按指纹规则扫描、查找JetBrains许可证服务器。
+//!
+//! 命令行程序只负责解析参数，扫描的流程都在这个库中：
+//! 用[`utils::network::process_ip_cidr_hosts`]展开输入的地址和CIDR，
+//! 用[`utils::dns`]把域名解析成目标，再交给[`Scanner`]扫描，
+//! 结果可以逐条读取，也可以写入[`utils::files::ResultSink`]（CSV、JSON、SQLite、HTML报告等）。
+//!
+//! ```no_run
+//! use curl_cdn_cgi_trace_rust::{ utils, Scanner, Target };
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let lines = vec!["104.16.0.0/24".to_string()];
+//! let targets: Vec<Target> = utils::network::process_ip_cidr_hosts(lines, 20, 5, 42)
+//!     .into_iter()
+//!     .map(Target::from)
+//!     .collect();
+//! let airports = utils::locations::load_airports(false).await?;
+//! let mut scan = Scanner::new(targets)
+//!     .airports(airports)
+//!     .sink(Box::new(utils::files::JsonSink::new("output.json", Vec::new(), utils::files::WriteMode::Overwrite, false)))
+//!     .scan()?;
+//! for (_, result) in scan.by_ref() {
+//!     match result {
+//!         Ok(record) => println!("{} {}", record.ip, record.colo),
+//!         Err(reason) => eprintln!("{}", reason),
+//!     }
+//! }
+//! scan.finish()?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod utils;
+
+pub use utils::models::{ Airport, Record, ScanMode, Target };
+pub use utils::scanner::{ Finished, Scan, Scanner };
@@ -1,16 +1,66 @@
-mod utils;
+use curl_cdn_cgi_trace_rust::{ utils, Scanner };
 
-use crate::utils::models::Airport;
-use std::{ fs::{ self }, sync::{ mpsc, Arc, Mutex }, time::Instant };
-use reqwest::Error;
-use threadpool::ThreadPool;
-use clap::Parser;
-// use clap::CommandFactory;
+use utils::checkpoint::Checkpoint;
+use utils::columns::{ Column, Filter, SortKey };
+use utils::files::{ MergeKeep, OutputFormat, WriteMode };
+use utils::fingerprint::Rule;
+use utils::i18n::{ t, Lang };
+use utils::models::{ ScanMode, Target };
+use utils::prober::ProberKind;
+use utils::progress::{ Progress, Ui };
+use utils::summary::SummaryBy;
+use std::{ error::Error, fs::{ self }, net::SocketAddr, time::{ Duration, Instant } };
+use clap::{
+    error::ErrorKind,
+    parser::ValueSource,
+    ArgAction,
+    ArgMatches,
+    CommandFactory,
+    FromArgMatches,
+    Parser,
+    Subcommand,
+    ValueEnum,
+};
 
 /// 批量扫描是否走CloudFlare CDN的流量。
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
-struct Args {
+struct Cli {
+    /// 不指定子命令时执行scan，双击运行程序就是使用默认参数执行scan
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+// 所有子命令共用的参数
+#[derive(clap::Args, Debug, Clone)]
+struct GlobalArgs {
+    /// 帮助信息、CSV标题和日志使用的语言，不指定就跟随系统的区域设置
+    #[arg(long, value_enum, global = true)]
+    lang: Option<Lang>,
+
+    /// 配置文件(TOML)，不指定就依次查找当前目录和用户配置目录中的config.toml，命令行的参数优先于配置文件，
+    /// 配置文件中打开的开关可以用--tcp-prefilter=false这样的写法关闭
+    #[arg(long, global = true)]
+    config: Option<String>,
+
+    /// 使用配置文件中[profile.名称]的参数，覆盖配置文件顶层的参数
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// SQLite数据库文件，指定后每次扫描的参数和结果都保存到数据库中
+    #[arg(long, global = true)]
+    db: Option<String>,
+}
+
+// scan和jetbrains共用的参数：输入、输出、并发、限速、超时和中断恢复
+#[derive(clap::Args, Debug, Clone)]
+struct ScanOptions {
     /// 输入的数据文件(*.txt)，支持域名地址、IPv4/IPv6地址、IPv4/IPv6的CIDR的数据
     #[arg(short = 'f', default_value_t = format!("ips-v4.txt"))]
     file: String,
@@ -23,161 +73,624 @@ struct Args {
     #[arg(short, default_value_t = 1)]
     num: usize,
 
+    /// 随机生成IP使用的种子，相同的种子生成相同的IP，不指定就随机选一个
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// 输出的格式：csv、json(数组)、jsonl(每行一条记录)，JSON的字段名固定为英文，时长的单位是毫秒
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+
+    /// CSV输出的列及顺序，用逗号分隔，如ip,colo,latency_ms,status，不指定就使用扫描模式默认的列，不能用于JSON输出
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<Column>,
+
+    /// 排序的列，用逗号分隔，列名前面加"-"表示降序，如-verified,latency_ms
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    sort_by: Vec<SortKey>,
+
+    /// 过滤条件，可以指定多个，如colo=SJC、latency_ms<300、status!=403、cdn~cloud
+    #[arg(long)]
+    filter: Vec<Filter>,
+
+    /// 追加模式：保留输出文件中原有的结果，本次的结果加上扫描时间追加在后面，原CSV只缺少扫描时间列时先加上这一列，其它列不同时拒绝追加
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        default_value_t = false
+    )]
+    append: bool,
+
+    /// 合并模式：按地址(和端口)去重，newest保留本次扫描的结果，best保留排序靠前的结果，不写值就是newest，off不合并；
+    /// 保留原CSV中本次不输出的列，有不认识的列时拒绝合并
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "newest",
+        conflicts_with = "append"
+    )]
+    merge: Option<MergeArg>,
+
+    /// 扫描结束后汇总报告的分组方式：colo(数据中心)、country(国家/地区)、region(大区)
+    #[arg(long, value_enum, default_value_t = SummaryBy::Colo)]
+    summary_by: SummaryBy,
+
+    /// 汇总报告另外写入的文件，扩展名是.json就输出JSON，否则输出CSV
+    #[arg(long)]
+    summary: Option<String>,
+
+    /// 另外生成一个HTML报告(如report.html)，包含可排序的表格、延迟直方图、数据中心条形图和分布图，不依赖外部资源
+    #[arg(long)]
+    report: Option<String>,
+
+    /// 扫描过程中的界面：plain(只输出日志)、progress(日志加进度条)、tui(全屏的实时面板)
+    #[arg(long, value_enum, default_value_t = Ui::Progress)]
+    ui: Ui,
+
     /// 同时并行执行的任务数量，拿多个地址并行执行curl命令
     #[arg(long, default_value_t = 50)]
     pool: u16,
 
-    /// 只扫描是否为jetbrains的许可证服务器
-    #[arg(long, default_value_t = false)]
-    jetbrains: bool,
+    /// 每秒最多开始扫描的地址数量(令牌桶)，不指定就不限速，只受--pool限制
+    #[arg(long, value_parser = utils::rate::parse_rate)]
+    rate: Option<f64>,
+
+    /// 同一个网段(IPv4的/24、IPv6的/48)每秒最多开始扫描的地址数量，避免集中请求同一个网段
+    #[arg(long, value_parser = utils::rate::parse_rate)]
+    per_prefix_rate: Option<f64>,
+
+    /// 发送探测请求的后端：curl命令，或者程序内置的HTTP/1.1客户端(不需要安装curl，只支持http://)
+    #[arg(long, value_enum, default_value_t = ProberKind::Curl)]
+    prober: ProberKind,
+
+    /// 连接超时的秒数，支持小数
+    #[arg(long, value_parser = utils::timeout::parse_seconds, default_value = "3")]
+    connect_timeout: Duration,
+
+    /// 每个请求总的超时秒数，支持小数
+    #[arg(long, value_parser = utils::timeout::parse_seconds, default_value = "10")]
+    max_time: Duration,
+
+    /// 超时、连接被重置等临时错误的重试次数(最多10次)，重试前的等待时间逐次翻倍，最长等待--max-time
+    #[arg(long, value_parser = utils::timeout::parse_retries, default_value_t = 0)]
+    retries: u32,
+
+    /// 自适应超时：成功的请求足够多以后，超时收紧到p90延迟的3倍，能大大缩短扫描大量无响应地址的时间
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        default_value_t = false
+    )]
+    adaptive_timeout: bool,
+
+    /// 两阶段扫描：先对目标端口做一次快速的TCP连接(同时连接的数量与--pool相同)，只把能连接上的地址交给HTTP扫描
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        default_value_t = false
+    )]
+    tcp_prefilter: bool,
+
+    /// TCP预筛选的连接超时秒数，支持小数
+    #[arg(long, value_parser = utils::timeout::parse_seconds, default_value = "1")]
+    tcp_timeout: Duration,
+
+    /// 按Ctrl-C中断扫描时，保存检查点(剩下的目标、种子、已扫描的地址)的文件
+    #[arg(long, default_value_t = format!("checkpoint.json"))]
+    checkpoint: String,
+
+    /// 从检查点文件继续之前中断的扫描，不再读取输入文件，结果追加到输出文件中，请使用与之前相同的参数
+    #[arg(long)]
+    resume: Option<String>,
 }
 
-static LOCATIONS: &str = "locations.json";
-static LOCATIONS_URL: &str = "https://speed.cloudflare.com/locations";
+// scan：扫描cdn-cgi/trace，或者按指纹规则扫描
+#[derive(clap::Args, Debug, Clone)]
+struct ScanArgs {
+    #[command(flatten)]
+    options: ScanOptions,
 
-/// 用于下载locations.json文件
-async fn download_file(url: &str, path: &str) -> Result<(), Error> {
-    let response = reqwest::get(url).await?;
-    let content = response.text().await?;
-    fs::write(path, content).expect("Unable to write file");
-    Ok(())
+    /// 指纹识别模式：使用内置规则和规则文件中的规则扫描，输出匹配到的规则名称
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        default_value_t = false
+    )]
+    fingerprint: bool,
+
+    /// 指纹规则文件(*.json)，文件不存在就只使用内置的规则
+    #[arg(long, default_value_t = format!("fingerprints.json"))]
+    rules: String,
+
+    /// 解析域名使用的DNS服务器(如1.1.1.1、127.0.0.1:5353)，不指定就使用系统的DNS配置
+    #[arg(long, value_parser = utils::dns::parse_nameserver)]
+    dns: Option<SocketAddr>,
+
+    /// 优选域名模式：所有IP都以这个域名作为Host/SNI去请求，并校验trace中的h=字段
+    #[arg(long, conflicts_with = "fingerprint")]
+    host: Option<String>,
+
+    /// 从CloudFlare官网下载最新的IP段，更新cloudflare-ranges.txt文件
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        default_value_t = false
+    )]
+    update_cf_ranges: bool,
+
+    // jetbrains子命令的--ca-file，scan没有这个参数
+    #[arg(skip)]
+    ca_file: String,
+}
+
+// jetbrains：扫描JetBrains许可证服务器，只使用内置的JetBrains规则
+#[derive(clap::Args, Debug, Clone)]
+struct JetbrainsArgs {
+    #[command(flatten)]
+    options: ScanOptions,
+
+    /// JetBrains许可证服务器的根证书(PEM)，用来校验握手响应的签名，文件不存在就不校验，签名验证都是false
+    #[arg(long, default_value_t = format!("jetbrains-ca.pem"))]
+    ca_file: String,
+}
+
+impl From<JetbrainsArgs> for ScanArgs {
+    fn from(args: JetbrainsArgs) -> Self {
+        ScanArgs {
+            options: args.options,
+            fingerprint: false,
+            rules: String::new(),
+            dns: None,
+            host: None,
+            update_cf_ranges: false,
+            ca_file: args.ca_file,
+        }
+    }
+}
+
+// expand：只展开输入文件中的地址，不扫描
+#[derive(clap::Args, Debug, Clone)]
+struct ExpandArgs {
+    /// 输入的数据文件(*.txt)，支持域名地址、IPv4/IPv6地址、IPv4/IPv6的CIDR的数据
+    #[arg(short = 'f', default_value_t = format!("ips-v4.txt"))]
+    file: String,
+
+    /// 如果是IPv4/IPv6的CIDR，就它的范围，随机生成指定数量的IP地址
+    #[arg(short, default_value_t = 1)]
+    num: usize,
+
+    /// 随机生成IP使用的种子，相同的种子生成相同的IP，不指定就随机选一个
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// 展开的地址写入这个文件，不指定就输出到标准输出
+    #[arg(short)]
+    output: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// 扫描是否走CloudFlare CDN的流量(默认的子命令)，--fingerprint按指纹规则扫描
+    Scan(ScanArgs),
+    /// 扫描JetBrains许可证服务器，进行握手并用--ca-file的根证书校验响应的签名
+    Jetbrains(JetbrainsArgs),
+    /// 列出CloudFlare全部的数据中心(locations.json)
+    Locations {
+        /// 重新下载locations.json
+        #[arg(
+            long,
+            action = ArgAction::Set,
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "true",
+            default_value_t = false
+        )]
+        update: bool,
+    },
+    /// 比较两次扫描的结果文件(CSV/JSON/JSON Lines)，列出新出现、消失、数据中心变化和延迟变化的地址
+    Diff {
+        /// 旧的结果文件
+        old: String,
+        /// 新的结果文件
+        new: String,
+        /// 延迟变化超过这个值(毫秒)才报告
+        #[arg(long, default_value_t = 50)]
+        threshold: u64,
+    },
+    /// 按当前的-n和种子只展开输入文件中的地址，不扫描，每一行展开的数量输出到标准错误
+    Expand(ExpandArgs),
+    /// 查询数据中心的代码(如SJC)或城市
+    Lookup {
+        /// 数据中心的代码或城市名称
+        colo: String,
+    },
+    /// 查看一个地址在历次扫描中的延迟和数据中心（需要--db，默认scans.db）
+    History {
+        /// IP地址或域名
+        ip: String,
+    },
+}
+
+// --merge的值，off用来在命令行关闭配置文件中的merge
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum MergeArg {
+    Newest,
+    Best,
+    Off,
+}
+
+impl MergeArg {
+    fn keep(self) -> Option<MergeKeep> {
+        match self {
+            MergeArg::Newest => Some(MergeKeep::Newest),
+            MergeArg::Best => Some(MergeKeep::Best),
+            MergeArg::Off => None,
+        }
+    }
+}
+
+static DEFAULT_DB: &str = "scans.db";
+
+// 解析命令行参数，配置文件中的参数插入到命令行参数的前面(有子命令时插入到子命令的后面)，
+// 命令行中已经指定的参数以命令行为准
+fn parse_args() -> Result<ArgMatches, Box<dyn Error>> {
+    let matches = utils::i18n::localize_command(Cli::command()).get_matches();
+    // 子命令之前只能写全局参数(--lang、--config等)，扫描参数要写在子命令之后
+    if let Some((name, _)) = matches.subcommand() {
+        let mut root = Cli::command();
+        let misplaced = root
+            .get_arguments()
+            .filter(|arg| !arg.is_global_set())
+            .find(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine));
+        if let Some(arg) = misplaced {
+            let flag = arg
+                .get_long()
+                .map_or_else(|| format!("-{}", arg.get_short().unwrap_or_default()), |long| format!("--{}", long));
+            let message = format!(
+                "{} {}: {}",
+                name,
+                flag,
+                t("这个参数需要写在子命令之后", "this argument must come after the subcommand")
+            );
+            root.error(ErrorKind::ArgumentConflict, message).exit();
+        }
+    }
+    let config = utils::config::find_config(matches.get_one::<String>("config").map(String::as_str));
+    let profile = matches.get_one::<String>("profile").map(String::as_str);
+    let path = match (config, profile) {
+        (Some(path), _) => path,
+        (None, Some(_)) => {
+            return Err(t("--profile需要配置文件", "--profile requires a config file").into());
+        }
+        (None, None) => {
+            return Ok(matches);
+        }
+    };
+    let values = utils::config::load_values(&path, profile)?;
+    let mut root = Cli::command();
+    root.build();
+    let argv = utils::config::splice_config_args(&root, &matches, std::env::args().collect(), &values);
+    Ok(utils::i18n::localize_command(Cli::command()).get_matches_from(argv))
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 检测curl是否安装，没有安装就退出程序
-    utils::curl::check_curl_installed();
     // 初始化日记
     utils::logger::init_logger()?;
 
-    let args = Args::parse();
+    // 先确定语言，再生成对应语言的帮助信息：命令行的--lang、配置文件中的lang、系统的区域设置
+    let lang = utils::i18n
+        ::lang_from_args()
+        .or_else(utils::config::lang_from_config)
+        .unwrap_or_else(utils::i18n::system_lang);
+    utils::i18n::set_lang(lang);
+    let matches = parse_args()?;
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     /*
         检查是否未提供任何参数（程序名称除外）
         注释掉这个if条件，如果设置Args的默认参数值，双击编译后的exe程序会自动执行
     */
     // if std::env::args().len() <= 1 {
     //     // 显示帮助信息
-    //     let mut cmd = Args::command();
+    //     let mut cmd = Cli::command();
     //     cmd.print_help().unwrap();
     //     std::process::exit(0);
     // }
+    match cli.command {
+        Some(Commands::Scan(args)) => {
+            let mode = if args.fingerprint { ScanMode::Fingerprint } else { ScanMode::Cdn };
+            run_scan(args, mode, &cli.global).await
+        }
+        Some(Commands::Jetbrains(args)) => run_scan(args.into(), ScanMode::Jetbrains, &cli.global).await,
+        Some(Commands::Locations { update }) => {
+            utils::locations::print_locations(&utils::locations::load_airports(update).await?);
+            Ok(())
+        }
+        Some(Commands::Diff { old, new, threshold }) => {
+            let old = utils::files::read_records(&old)?;
+            let new = utils::files::read_records(&new)?;
+            utils::diff::print_diff(old, new, threshold);
+            Ok(())
+        }
+        Some(Commands::Expand(args)) => run_expand(args),
+        Some(Commands::Lookup { colo }) => {
+            utils::locations::lookup(&utils::locations::load_airports(false).await?, &colo);
+            Ok(())
+        }
+        Some(Commands::History { ip }) => {
+            utils::db::print_history(cli.global.db.as_deref().unwrap_or(DEFAULT_DB), &ip)?;
+            Ok(())
+        }
+        // 没有子命令就是scan
+        None => {
+            let mode = if cli.scan.fingerprint { ScanMode::Fingerprint } else { ScanMode::Cdn };
+            run_scan(cli.scan, mode, &cli.global).await
+        }
+    }
+}
 
-    // 加载locations.json文件
-    let locations = match fs::read_to_string(LOCATIONS) {
-        Ok(data) => data,
-        Err(_) => {
-            // 文件不存在或读取失败，下载文件
-            download_file(LOCATIONS_URL, LOCATIONS).await?;
-            fs::read_to_string(LOCATIONS).expect("Unable to read file")
+// 只展开输入文件，地址的顺序与扫描时相同；每一行展开的数量输出到标准错误，不影响管道中的地址列表
+fn run_expand(args: ExpandArgs) -> Result<(), Box<dyn Error>> {
+    let lines = utils::files::read_text_file(&args.file)?;
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let expanded = utils::network::expand_lines(lines, 20, args.num, seed);
+    let addrs = utils::network::shuffle_expanded(&expanded, seed);
+    let mut content = addrs.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    match &args.output {
+        Some(path) => fs::write(path, content)?,
+        None => print!("{}", content),
+    }
+
+    let width = expanded
+        .iter()
+        .map(|(line, _)| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    eprintln!("{:<width$}  {:>8}", t("输入", "input"), t("数量", "count"), width = width);
+    for (line, ips) in &expanded {
+        eprintln!("{:<width$}  {:>8}", line, ips.len(), width = width);
+    }
+    eprintln!(
+        "\n{}: {}, {}: {}, -n: {}, {}: {}",
+        t("行数", "Lines"),
+        expanded.len(),
+        t("地址总数", "Addresses"),
+        addrs.len(),
+        args.num,
+        t("种子", "seed"),
+        seed
+    );
+    if let Some(path) = &args.output {
+        eprintln!("{}: {}", t("已写入", "Written to"), path);
+    }
+    Ok(())
+}
+
+async fn run_scan(args: ScanArgs, mode: ScanMode, global: &GlobalArgs) -> Result<(), Box<dyn Error>> {
+    let options = &args.options;
+    // JSON输出全部字段，--columns只能用于CSV(参数也可能来自配置文件，所以在解析之后检查)
+    if !options.columns.is_empty() && options.format != OutputFormat::Csv {
+        let message = t(
+            "--columns只能用于CSV输出，JSON(--format json/jsonl)输出全部字段",
+            "--columns only applies to CSV output, JSON (--format json/jsonl) contains every field"
+        );
+        Cli::command().error(ErrorKind::ArgumentConflict, message).exit();
+    }
+    // 使用curl扫描时，检测curl是否安装，没有安装就退出程序
+    if options.prober == ProberKind::Curl {
+        utils::curl::check_curl_installed();
+    }
+    let airports = utils::locations::load_airports(false).await?;
+
+    // 更新CloudFlare的IP段文件，失败就继续使用本地或内置的IP段
+    if args.update_cf_ranges {
+        match utils::cdn::update_cloudflare_ranges(utils::cdn::CLOUDFLARE_RANGES).await {
+            Ok(count) => println!("{}: {}", t("已更新的CloudFlare IP段", "Cloudflare ranges updated"), count),
+            Err(e) => eprintln!("{}: {}", t("更新CloudFlare的IP段失败", "Failed to update Cloudflare ranges"), e),
         }
-    };
+    }
 
-    // 解析为 Airport 结构体
-    let airports: Vec<Airport> = serde_json::from_str(&locations)?;
+    let stop = utils::checkpoint::StopHandle::new();
+    utils::checkpoint::install_signal_handler(stop.clone())?;
     let start_time = Instant::now();
-    match utils::files::read_text_file(&args.file) {
+    // 从检查点恢复时，不需要再读取输入文件
+    let checkpoint = options.resume.as_deref().map(Checkpoint::load).transpose()?;
+    let input = match &checkpoint {
+        Some(_) => Ok(Vec::new()),
+        None => utils::files::read_text_file(&options.file),
+    };
+    match input {
         Ok(line) => {
-            let data_vec = utils::network::process_ip_cidr_hosts(line, 20, args.num);
-
-            println!("开始扫描 cdn-cgi/trace 中...\n");
-            let (tx_method, rx_method) = mpsc::channel();
-            let pool_method = ThreadPool::new(args.pool.into());
-            let arc_addr = Arc::new(Mutex::new(data_vec));
-            for addr in arc_addr.lock().unwrap().iter() {
-                let tx_method = tx_method.clone();
-                let cloned_addr = addr.clone();
-                let airports = airports.clone();
-                pool_method.execute(move || {
-                    match
-                        utils::curl::run_command_and_process_data(
-                            &cloned_addr,
-                            airports,
-                            args.jetbrains
-                        )
-                    {
-                        Ok(record) => tx_method.send(record).unwrap(),
-                        Err(_e) => {}
-                    }
-                });
-            }
-            drop(tx_method);
-
-            let mut records: Vec<Vec<String>> = Vec::new();
-            // 读取通道数据，添加到records向量中
-            rx_method.iter().for_each(|item| {
-                let delay = item.delay.as_millis();
-                let vec = vec![
-                    item.ip.clone(),
-                    item.colo.clone(),
-                    item.country.clone(),
-                    item.region.clone(),
-                    item.city.clone(),
-                    delay.to_string(),
-                    item.http_status_code.to_string()
-                ];
-                records.push(vec);
-            });
-
-            // ----------------------------------------------------------------------------
-
-            // 按延迟(毫秒)排序，注意：延迟没有单位ms和s的字符串
-            records.sort_by(|a, b| {
-                let latency_a: i32 = a[5].parse().unwrap_or(i32::MAX);
-                let latency_b: i32 = b[5].parse().unwrap_or(i32::MAX);
-                latency_a.cmp(&latency_b) // 比较
-            });
-
-            // 将标题行添加到开头
-            records.insert(
-                0,
-                vec![
-                    "IP地址".to_string(),
-                    "数据中心".to_string(),
-                    "alpha-2".to_string(),
-                    "地区".to_string(),
-                    "城市".to_string(),
-                    "延迟(毫秒)".to_string(), // 该值仅供参考，只是执行curl命令的耗时
-                    "HTTP状态码".to_string()
-                ]
-            );
+            let seed = match &checkpoint {
+                Some(checkpoint) => checkpoint.seed,
+                None => options.seed.unwrap_or_else(rand::random),
+            };
+            let data_vec = utils::network::process_ip_cidr_hosts(line, 20, options.num, seed);
+            // 域名解析成全部的A/AAAA记录，指纹识别模式直接请求原地址，不需要解析
+            let mut completed_addrs: Vec<String> = Vec::new();
+            let targets: Vec<Target> = match (checkpoint, &args.host, mode != ScanMode::Cdn) {
+                (Some(checkpoint), _, _) => {
+                    println!(
+                        "{}: {} / {}",
+                        t("从检查点继续，已扫描/剩下的目标", "Resuming from checkpoint, done/remaining"),
+                        checkpoint.completed.len(),
+                        checkpoint.remaining.len()
+                    );
+                    completed_addrs = checkpoint.completed;
+                    checkpoint.remaining
+                }
+                (None, Some(host), _) => utils::dns::pin_targets_to_host(data_vec, host),
+                (None, None, true) => data_vec.into_iter().map(Target::from).collect(),
+                (None, None, false) => utils::dns::resolve_targets(data_vec, args.dns, options.pool.into()).await,
+            };
 
-            // ----------------------------------------------------------------------------
-            match args.jetbrains {
+            let targets = match options.tcp_prefilter {
                 true => {
-                    // 过滤掉不需要的列
-                    let mut jetbrains_records: Vec<Vec<String>> = Vec::new();
-
-                    // 添加标题，包含 "jetBrains激活服务器" 列
-                    let header: Vec<String> = vec![
-                        "IP地址".to_string(),
-                        "延迟(毫秒)".to_string(),
-                        "HTTP状态码".to_string(),
-                        "jetBrains激活服务器".to_string()
-                    ];
-                    jetbrains_records.push(header);
-
-                    // 遍历原始记录并保留需要的列，以及插入新列
-                    for row in records.iter().skip(1) {
-                        let mut new_row: Vec<String> = Vec::new();
-                        new_row.push(row[0].clone()); // 添加第一列
-                        new_row.push(row[row.len() - 2].clone()); // 添加倒数第二列
-                        new_row.push(row[row.len() - 1].clone()); // 添加倒数第一列
-                        new_row.push("true".to_string()); // JetBrains License server
-                        jetbrains_records.push(new_row);
-                    }
+                    let before = targets.len();
+                    let targets = utils::prefilter::tcp_prefilter(
+                        targets,
+                        mode,
+                        options.tcp_timeout,
+                        options.pool.into(),
+                        &stop
+                    ).await;
+                    println!(
+                        "{}: {}/{}",
+                        t("TCP预筛选，能连接的地址/全部地址", "TCP pre-filter, reachable/all"),
+                        targets.len(),
+                        before
+                    );
+                    targets
+                }
+                false => targets,
+            };
+
+            // --fingerprint使用规则文件中的规则，jetbrains模式由Scanner选择内置的JetBrains规则
+            let rules: Option<Vec<Rule>> = match mode {
+                ScanMode::Fingerprint => Some(utils::fingerprint::load_rules(&args.rules)?),
+                _ => None,
+            };
+
+            match (&rules, mode) {
+                (Some(rules), _) =>
+                    println!(
+                        "{} ({})...\n",
+                        t("开始扫描指纹规则中", "Scanning fingerprint rules"),
+                        rules.len()
+                    ),
+                (None, ScanMode::Jetbrains) =>
+                    println!("{}...\n", t("开始扫描JetBrains许可证服务器中", "Scanning JetBrains license servers")),
+                (None, _) => println!("{}...\n", t("开始扫描 cdn-cgi/trace 中", "Scanning cdn-cgi/trace")),
+            }
+            let total = targets.len();
 
-                    // 写入CSV文件
-                    utils::files::write_to_csv(&args.output, jetbrains_records)?;
+            // 边扫描边写入，扫描结束后再生成排序好的结果文件
+            let columns = match options.columns.is_empty() {
+                true => utils::columns::default_columns(mode),
+                false => options.columns.clone(),
+            };
+            let sort_by = match options.sort_by.is_empty() {
+                true => utils::columns::default_sort(mode),
+                false => options.sort_by.clone(),
+            };
+            // 继续之前的扫描时，保留中断时已经写入的结果
+            let merge = options.merge.and_then(MergeArg::keep);
+            let write_mode = match (options.resume.is_some(), options.append, merge) {
+                (_, _, Some(keep)) => WriteMode::Merge(keep),
+                (true, _, None) => WriteMode::Resume,
+                (false, true, None) => WriteMode::Append,
+                (false, false, None) => WriteMode::Overwrite,
+            };
+            let mut scanner = Scanner::new(targets)
+                .mode(mode)
+                .pool(options.pool.into())
+                .prober(utils::prober::create_prober(options.prober))
+                .airports(airports.clone())
+                .stop_handle(stop.clone())
+                .timeouts(
+                    utils::timeout::TimeoutPolicy::new(
+                        options.connect_timeout,
+                        options.max_time,
+                        options.retries,
+                        options.adaptive_timeout
+                    )
+                )
+                .rate(options.rate)
+                .per_prefix_rate(options.per_prefix_rate)
+                .sink(
+                    utils::files::create_sink(
+                        options.format,
+                        &options.output,
+                        columns.clone(),
+                        sort_by.clone(),
+                        write_mode
+                    )
+                );
+            if let Some(rules) = rules {
+                scanner = scanner.rules(rules);
+            }
+            if mode == ScanMode::Jetbrains {
+                match utils::jetbrains::load_trusted_certs(&args.ca_file)? {
+                    Some(certs) => {
+                        scanner = scanner.trusted_certs(certs);
+                    }
+                    None =>
+                        eprintln!(
+                            "{}: {}",
+                            t(
+                                "没有找到根证书，不校验握手响应的签名",
+                                "CA file not found, handshake signatures will not be verified"
+                            ),
+                            args.ca_file
+                        ),
                 }
-                false => {
-                    // 写入CSV文件
-                    utils::files::write_to_csv(&args.output, records)?;
+            }
+            for filter in &options.filter {
+                scanner = scanner.filter(filter.clone());
+            }
+            if let Some(report) = &options.report {
+                scanner = scanner.sink(
+                    Box::new(utils::report::ReportSink::new(report, columns, sort_by, airports))
+                );
+            }
+            scanner = scanner.sink(
+                Box::new(utils::summary::SummarySink::new(options.summary_by, options.summary.clone(), total))
+            );
+            if let Some(db) = &global.db {
+                let command_line = std::env::args().collect::<Vec<String>>().join(" ");
+                scanner = scanner.sink(Box::new(utils::db::SqliteSink::new(db, command_line, seed)));
+            }
+            let mut scan = scanner.scan()?;
+            let mut progress = Progress::new(options.ui, total)?;
+            for (_, result) in scan.by_ref() {
+                match result {
+                    Ok(record) => progress.record(&record),
+                    Err(reason) => progress.error(&reason),
                 }
             }
+            progress.finish();
+            let finished = scan.finish()?;
+
+            // 被中断就保存检查点，继续扫描完成后删除检查点
+            let checkpoint_path = options.resume.as_deref().unwrap_or(&options.checkpoint);
+            if stop.stopped() {
+                completed_addrs.extend(finished.completed.iter().map(Target::to_string));
+                let checkpoint = Checkpoint { seed, completed: completed_addrs, remaining: finished.remaining };
+                checkpoint.save(checkpoint_path)?;
+                println!(
+                    "\n{}: {} ({}: {})",
+                    t("扫描已中断，检查点保存到", "Scan interrupted, checkpoint saved to"),
+                    checkpoint_path,
+                    t("剩下的目标", "remaining targets"),
+                    checkpoint.remaining.len()
+                );
+                println!("{} --resume {}", t("继续扫描：", "To continue:"), checkpoint_path);
+            } else if options.resume.is_some() {
+                let _ = fs::remove_file(checkpoint_path);
+            }
         }
-        Err(e) => eprintln!("读取txt文件时发生错误: {}", e),
+        Err(e) => eprintln!("{}: {}", t("读取txt文件时发生错误", "Failed to read the input file"), e),
     }
-    println!("\n程序扫描的总时长: {:?}", start_time.elapsed());
+    println!("\n{}: {:?}", t("程序扫描的总时长", "Total scan time"), start_time.elapsed());
 
     Ok(())
 }
@@ -0,0 +1,242 @@
+use ipnetwork::IpNetwork;
+use std::{ error::Error, fs, net::IpAddr };
+
+pub static CLOUDFLARE_RANGES: &str = "cloudflare-ranges.txt";
+static CLOUDFLARE_RANGES_URLS: [&str; 2] = [
+    "https://www.cloudflare.com/ips-v4",
+    "https://www.cloudflare.com/ips-v6",
+];
+// 编译时内置的CloudFlare IP段，本地没有cloudflare-ranges.txt文件时使用
+static BUNDLED_CLOUDFLARE_RANGES: &str = include_str!("../../cloudflare-ranges.txt");
+
+// 解析响应头，名称统一转为小写
+pub fn parse_headers(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if name.is_empty() || name.contains(' ') || name.contains('=') {
+                return None;
+            }
+            Some((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+// 获取响应头的值，name必须是小写
+pub fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+// 识别结果
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub cdn: &'static str, // CDN名称
+    pub pop: String, // 边缘节点的代码，能映射到locations.json的IATA代码
+    pub signals: Vec<&'static str>, // 匹配到的检测依据
+}
+
+// CDN识别器，根据响应头和连接的IP判断是否走这个CDN
+pub trait CdnDetector: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // 返回匹配到的检测依据和POP代码，没有匹配就返回None
+    fn detect(&self, headers: &[(String, String)], ip: Option<IpAddr>) -> Option<Detection>;
+}
+
+// 内置的识别器，按顺序检测，第一个匹配的就是结果
+pub fn builtin_detectors(cloudflare_ranges: &str) -> Vec<Box<dyn CdnDetector>> {
+    vec![
+        Box::new(CloudflareDetector::load(cloudflare_ranges)),
+        Box::new(CloudFrontDetector),
+        Box::new(FastlyDetector),
+        Box::new(AkamaiDetector),
+        Box::new(GcoreDetector)
+    ]
+}
+
+// 依次使用识别器检测
+pub fn detect(
+    detectors: &[Box<dyn CdnDetector>],
+    headers: &[(String, String)],
+    ip: Option<IpAddr>
+) -> Option<Detection> {
+    detectors.iter().find_map(|detector| detector.detect(headers, ip))
+}
+
+fn detection(cdn: &'static str, pop: String, signals: Vec<&'static str>) -> Option<Detection> {
+    if signals.is_empty() { None } else { Some(Detection { cdn, pop, signals }) }
+}
+
+// CloudFlare：CF-RAY: 8abc1234def-SJC
+pub struct CloudflareDetector {
+    ranges: Vec<IpNetwork>,
+}
+
+impl CloudflareDetector {
+    // 优先读取本地的IP段文件，没有就使用内置的IP段
+    pub fn load(path: &str) -> Self {
+        let text = fs::read_to_string(path).unwrap_or_else(|_| BUNDLED_CLOUDFLARE_RANGES.to_string());
+        let ranges = text
+            .lines()
+            .filter_map(|line| line.trim().parse::<IpNetwork>().ok())
+            .collect();
+        CloudflareDetector { ranges }
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+}
+
+impl CdnDetector for CloudflareDetector {
+    fn name(&self) -> &'static str {
+        "cloudflare"
+    }
+
+    fn detect(&self, headers: &[(String, String)], ip: Option<IpAddr>) -> Option<Detection> {
+        let mut signals: Vec<&'static str> = Vec::new();
+        let ray = header(headers, "cf-ray");
+        if ray.is_some() {
+            signals.push("cf-ray");
+        }
+        if header(headers, "server").is_some_and(|v| v.eq_ignore_ascii_case("cloudflare")) {
+            signals.push("server");
+        }
+        if header(headers, "cf-cache-status").is_some() {
+            signals.push("cf-cache-status");
+        }
+        if ip.is_some_and(|ip| self.contains(ip)) {
+            signals.push("ip-range");
+        }
+        // 获取 `"-"` 后面的部分
+        let pop = ray
+            .and_then(|ray| ray.rsplit_once('-'))
+            .map(|(_, colo)| colo.to_string())
+            .unwrap_or_default();
+        detection(self.name(), pop, signals)
+    }
+}
+
+// CloudFront：X-Amz-Cf-Pop: SFO53-P1，前三个字母是IATA代码
+pub struct CloudFrontDetector;
+
+impl CdnDetector for CloudFrontDetector {
+    fn name(&self) -> &'static str {
+        "cloudfront"
+    }
+
+    fn detect(&self, headers: &[(String, String)], _ip: Option<IpAddr>) -> Option<Detection> {
+        let mut signals: Vec<&'static str> = Vec::new();
+        let pop = header(headers, "x-amz-cf-pop");
+        if pop.is_some() {
+            signals.push("x-amz-cf-pop");
+        }
+        if header(headers, "x-amz-cf-id").is_some() {
+            signals.push("x-amz-cf-id");
+        }
+        if header(headers, "via").is_some_and(|v| v.contains("cloudfront.net")) {
+            signals.push("via");
+        }
+        let pop = pop
+            .map(|pop| pop.chars().take_while(|c| c.is_ascii_alphabetic()).collect::<String>())
+            .unwrap_or_default();
+        detection(self.name(), pop.to_ascii_uppercase(), signals)
+    }
+}
+
+// Fastly：X-Served-By: cache-sjc10041-SJC, cache-fra-eddf8230026-FRA，最后一个缓存节点是边缘节点
+pub struct FastlyDetector;
+
+impl CdnDetector for FastlyDetector {
+    fn name(&self) -> &'static str {
+        "fastly"
+    }
+
+    fn detect(&self, headers: &[(String, String)], _ip: Option<IpAddr>) -> Option<Detection> {
+        let mut signals: Vec<&'static str> = Vec::new();
+        let served_by = header(headers, "x-served-by").filter(|v| v.starts_with("cache-"));
+        if served_by.is_some() {
+            signals.push("x-served-by");
+        }
+        if header(headers, "x-fastly-request-id").is_some() {
+            signals.push("x-fastly-request-id");
+        }
+        let pop = served_by
+            .and_then(|v| v.rsplit(',').next())
+            .and_then(|node| node.trim().rsplit_once('-'))
+            .map(|(_, pop)| pop.to_string())
+            .unwrap_or_default();
+        detection(self.name(), pop, signals)
+    }
+}
+
+// Akamai：响应头中不带节点信息，只做识别
+pub struct AkamaiDetector;
+
+impl CdnDetector for AkamaiDetector {
+    fn name(&self) -> &'static str {
+        "akamai"
+    }
+
+    fn detect(&self, headers: &[(String, String)], _ip: Option<IpAddr>) -> Option<Detection> {
+        let mut signals: Vec<&'static str> = Vec::new();
+        if header(headers, "server").is_some_and(|v| v.starts_with("Akamai")) {
+            signals.push("server");
+        }
+        if header(headers, "x-akamai-transformed").is_some() {
+            signals.push("x-akamai-transformed");
+        }
+        if header(headers, "akamai-grn").is_some() {
+            signals.push("akamai-grn");
+        }
+        detection(self.name(), String::new(), signals)
+    }
+}
+
+// Gcore：X-ID: fra1-hw-edge-gc17，开头的字母是IATA代码
+pub struct GcoreDetector;
+
+impl CdnDetector for GcoreDetector {
+    fn name(&self) -> &'static str {
+        "gcore"
+    }
+
+    fn detect(&self, headers: &[(String, String)], _ip: Option<IpAddr>) -> Option<Detection> {
+        let mut signals: Vec<&'static str> = Vec::new();
+        let x_id = header(headers, "x-id").filter(|v| {
+            v.rsplit('-')
+                .next()
+                .is_some_and(|node| node.starts_with("gc"))
+        });
+        if x_id.is_some() {
+            signals.push("x-id");
+        }
+        if header(headers, "server").is_some_and(|v| v.to_ascii_lowercase().contains("gcore")) {
+            signals.push("server");
+        }
+        let pop = x_id
+            .map(|v| v.chars().take_while(|c| c.is_ascii_alphabetic()).collect::<String>())
+            .unwrap_or_default();
+        detection(self.name(), pop.to_ascii_uppercase(), signals)
+    }
+}
+
+// 从CloudFlare官网下载最新的IP段，更新本地的IP段文件
+pub async fn update_cloudflare_ranges(path: &str) -> Result<usize, Box<dyn Error>> {
+    let mut ranges: Vec<String> = Vec::new();
+    for url in CLOUDFLARE_RANGES_URLS {
+        let content = reqwest::get(url).await?.error_for_status()?.text().await?;
+        ranges.extend(
+            content
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| line.parse::<IpNetwork>().is_ok())
+                .map(|line| line.to_string())
+        );
+    }
+    fs::write(path, ranges.join("\n") + "\n")?;
+    Ok(ranges.len())
+}
@@ -0,0 +1,75 @@
+use crate::utils::i18n::t;
+use crate::utils::models::Target;
+
+use log::warn;
+use serde::{ Deserialize, Serialize };
+use std::{ error::Error, fs, sync::{ atomic::{ AtomicBool, Ordering }, Arc }, thread, time::{ Duration, Instant } };
+
+// 停止扫描的开关：停止后不再开始新的扫描，等待正在进行的扫描结束，克隆的开关共用同一个状态
+#[derive(Clone, Default)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn new() -> Self {
+        StopHandle::default()
+    }
+
+    // 停止扫描，返回之前是否已经停止
+    pub fn stop(&self) -> bool {
+        self.0.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    // 等待duration，每50毫秒检查一次，停止后提前返回；返回是否等满了整个时长
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        loop {
+            if self.stopped() {
+                return false;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return true;
+            }
+            thread::sleep(remaining.min(Duration::from_millis(50)));
+        }
+    }
+}
+
+// 收到Ctrl-C(SIGINT)或SIGTERM后关闭stop，第一次中断停止派发任务，第二次中断直接退出
+pub fn install_signal_handler(stop: StopHandle) -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(move || {
+        if stop.stop() {
+            std::process::exit(130);
+        }
+        warn!(
+            "{}",
+            t(
+                "正在停止：等待进行中的扫描结束后保存结果和检查点，再按一次Ctrl-C直接退出",
+                "Stopping: waiting for in-flight probes, then saving results and a checkpoint; press Ctrl-C again to quit now"
+            )
+        );
+    })
+}
+
+// 中断时保存的检查点，--resume从这里继续扫描剩下的目标
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Checkpoint {
+    pub seed: u64, // 生成IP使用的种子
+    pub completed: Vec<String>, // 已经扫描过的地址
+    pub remaining: Vec<Target>, // 还没有扫描的目标
+}
+
+impl Checkpoint {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,347 @@
+use crate::utils::i18n::t;
+use crate::utils::models::{ Record, ScanMode };
+
+use std::{ cmp::Ordering, str::FromStr, time::Duration };
+
+// 输出的列，名称可以用于--columns、--sort-by和--filter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    Ip,
+    Domain,
+    ResolvedIp,
+    Colo,
+    Country,
+    Region,
+    City,
+    LatencyMs,
+    Status,
+    Cdn,
+    Detection,
+    Fingerprints,
+    SaltEchoed,
+    Verified,
+    ServerVersion,
+    Licensee,
+    LicenseType,
+    LeasePeriodS,
+    HandshakeMs,
+    ScannedAt,
+}
+
+static COLUMNS: &[(Column, &str)] = &[
+    (Column::Ip, "ip"),
+    (Column::Domain, "domain"),
+    (Column::ResolvedIp, "resolved_ip"),
+    (Column::Colo, "colo"),
+    (Column::Country, "country"),
+    (Column::Region, "region"),
+    (Column::City, "city"),
+    (Column::LatencyMs, "latency_ms"),
+    (Column::Status, "status"),
+    (Column::Cdn, "cdn"),
+    (Column::Detection, "detection"),
+    (Column::Fingerprints, "fingerprints"),
+    (Column::SaltEchoed, "salt_echoed"),
+    (Column::Verified, "verified"),
+    (Column::ServerVersion, "server_version"),
+    (Column::Licensee, "licensee"),
+    (Column::LicenseType, "license_type"),
+    (Column::LeasePeriodS, "lease_period_s"),
+    (Column::HandshakeMs, "handshake_ms"),
+    (Column::ScannedAt, "scanned_at"),
+];
+
+impl Column {
+    pub fn name(self) -> &'static str {
+        COLUMNS.iter()
+            .find(|(column, _)| *column == self)
+            .map(|(_, name)| *name)
+            .unwrap_or_default()
+    }
+
+    // CSV的标题(中文, 英文)
+    fn headers(self) -> (&'static str, &'static str) {
+        match self {
+            Column::Ip => ("IP地址", "IP"),
+            Column::Domain => ("域名", "Domain"),
+            Column::ResolvedIp => ("解析IP", "Resolved IP"),
+            Column::Colo => ("数据中心", "Colo"),
+            Column::Country => ("alpha-2", "alpha-2"),
+            Column::Region => ("地区", "Region"),
+            Column::City => ("城市", "City"),
+            Column::LatencyMs => ("延迟(毫秒)", "Latency (ms)"), // 该值仅供参考，只是执行curl命令的耗时
+            Column::Status => ("HTTP状态码", "HTTP Status"),
+            Column::Cdn => ("CDN", "CDN"),
+            Column::Detection => ("检测依据", "Detection"),
+            Column::Fingerprints => ("匹配规则", "Matched Rules"),
+            Column::SaltEchoed => ("返回了salt", "Salt Echoed"),
+            Column::Verified => ("签名验证", "Signature Verified"),
+            Column::ServerVersion => ("服务器版本", "Server Version"),
+            Column::Licensee => ("被授权者", "Licensee"),
+            Column::LicenseType => ("许可证类型", "License Type"),
+            Column::LeasePeriodS => ("租期(秒)", "Lease Period (s)"),
+            Column::HandshakeMs => ("握手耗时(毫秒)", "Handshake Time (ms)"),
+            Column::ScannedAt => ("扫描时间", "Scanned At"),
+        }
+    }
+
+    // CSV的标题
+    pub fn header(self) -> &'static str {
+        let (zh, en) = self.headers();
+        t(zh, en)
+    }
+
+    // 根据CSV的标题找到对应的列，中文、英文标题和列名都可以识别
+    pub fn from_header(header: &str) -> Option<Column> {
+        // 旧版本的"握手验证"列，就是现在的"返回了salt"
+        if ["握手验证", "Handshake Verified"].contains(&header) {
+            return Some(Column::SaltEchoed);
+        }
+        COLUMNS.iter()
+            .map(|(column, _)| *column)
+            .find(|column| {
+                let (zh, en) = column.headers();
+                header == zh || header == en || header == column.name()
+            })
+    }
+
+    pub fn value(self, record: &Record) -> String {
+        let server = record.license_server.as_ref();
+        match self {
+            Column::Ip => record.ip.clone(),
+            Column::Domain => record.domain.clone(),
+            Column::ResolvedIp => record.resolved_ip.clone(),
+            Column::Colo => record.colo.clone(),
+            Column::Country => record.country.clone(),
+            Column::Region => record.region.clone(),
+            Column::City => record.city.clone(),
+            Column::LatencyMs => record.delay.as_millis().to_string(),
+            Column::Status => record.http_status_code.clone(),
+            Column::Cdn => record.cdn.clone(),
+            Column::Detection => record.detection.clone(),
+            Column::Fingerprints => record.fingerprints.join("+"),
+            Column::SaltEchoed => server.is_some_and(|s| s.salt_echoed).to_string(),
+            Column::Verified => server.is_some_and(|s| s.verified).to_string(),
+            Column::ServerVersion => server.map(|s| s.version.clone()).unwrap_or_default(),
+            Column::Licensee => server.map(|s| s.licensee.clone()).unwrap_or_default(),
+            Column::LicenseType => server.map(|s| s.license_type.clone()).unwrap_or_default(),
+            Column::LeasePeriodS =>
+                server.map(|s| s.lease_period.as_secs()).unwrap_or_default().to_string(),
+            Column::HandshakeMs =>
+                server.map(|s| s.response_time.as_millis()).unwrap_or_default().to_string(),
+            Column::ScannedAt => record.scanned_at.clone(),
+        }
+    }
+
+    // 把CSV中的值写回Record，读取已有的结果文件时使用
+    pub fn set(self, record: &mut Record, value: &str) {
+        let millis = || Duration::from_millis(value.parse::<u64>().unwrap_or_default());
+        match self {
+            Column::Ip => record.ip = value.to_string(),
+            Column::Domain => record.domain = value.to_string(),
+            Column::ResolvedIp => record.resolved_ip = value.to_string(),
+            Column::Colo => record.colo = value.to_string(),
+            Column::Country => record.country = value.to_string(),
+            Column::Region => record.region = value.to_string(),
+            Column::City => record.city = value.to_string(),
+            Column::LatencyMs => record.delay = millis(),
+            Column::Status => record.http_status_code = value.to_string(),
+            Column::Cdn => record.cdn = value.to_string(),
+            Column::Detection => record.detection = value.to_string(),
+            Column::ScannedAt => record.scanned_at = value.to_string(),
+            Column::Fingerprints => {
+                record.fingerprints = value
+                    .split('+')
+                    .filter(|name| !name.is_empty())
+                    .map(|name| name.to_string())
+                    .collect();
+            }
+            _ => {
+                let server = record.license_server.get_or_insert_with(Default::default);
+                match self {
+                    Column::SaltEchoed => server.salt_echoed = value == "true",
+                    Column::Verified => server.verified = value == "true",
+                    Column::ServerVersion => server.version = value.to_string(),
+                    Column::Licensee => server.licensee = value.to_string(),
+                    Column::LicenseType => server.license_type = value.to_string(),
+                    Column::LeasePeriodS => {
+                        server.lease_period = Duration::from_secs(value.parse().unwrap_or_default());
+                    }
+                    Column::HandshakeMs => server.response_time = millis(),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        COLUMNS.iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(s.trim()))
+            .map(|(column, _)| *column)
+            .ok_or_else(|| {
+                let names: Vec<&str> = COLUMNS.iter()
+                    .map(|(_, name)| *name)
+                    .collect();
+                format!("{}: {} ({})", t("未知的列", "unknown column"), s, names.join(", "))
+            })
+    }
+}
+
+// 不同扫描模式默认输出的列
+pub fn default_columns(mode: ScanMode) -> Vec<Column> {
+    match mode {
+        // 包含许可证服务器握手的结果
+        ScanMode::Jetbrains =>
+            vec![
+                Column::Ip,
+                Column::LatencyMs,
+                Column::Status,
+                Column::Fingerprints,
+                Column::SaltEchoed,
+                Column::Verified,
+                Column::ServerVersion,
+                Column::Licensee,
+                Column::LicenseType,
+                Column::LeasePeriodS,
+                Column::HandshakeMs
+            ],
+        // 包含 "匹配规则" 列
+        ScanMode::Fingerprint =>
+            vec![Column::Ip, Column::LatencyMs, Column::Status, Column::Fingerprints],
+        ScanMode::Cdn =>
+            vec![
+                Column::Ip,
+                Column::Domain,
+                Column::ResolvedIp,
+                Column::Colo,
+                Column::Country,
+                Column::Region,
+                Column::City,
+                Column::LatencyMs,
+                Column::Status,
+                Column::Cdn,
+                Column::Detection
+            ],
+    }
+}
+
+// 两个值都是数字就按数字比较，否则按字符串比较
+fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+// 排序的列，名称前面加"-"表示降序，如 -verified
+#[derive(Clone, Copy, Debug)]
+pub struct SortKey {
+    pub column: Column,
+    pub descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().strip_prefix('-') {
+            Some(name) => Ok(SortKey { column: name.parse()?, descending: true }),
+            None => Ok(SortKey { column: s.parse()?, descending: false }),
+        }
+    }
+}
+
+// 默认按延迟(毫秒)排序，JetBrains模式下签名通过校验的排在前面，其次是握手返回了salt的
+pub fn default_sort(mode: ScanMode) -> Vec<SortKey> {
+    let latency = SortKey { column: Column::LatencyMs, descending: false };
+    match mode {
+        ScanMode::Jetbrains =>
+            vec![
+                SortKey { column: Column::Verified, descending: true },
+                SortKey { column: Column::SaltEchoed, descending: true },
+                latency
+            ],
+        _ => vec![latency],
+    }
+}
+
+// 按排序的列比较两条记录，Less表示a排在前面(更好)
+pub fn compare_records(a: &Record, b: &Record, keys: &[SortKey]) -> Ordering {
+    keys.iter()
+        .map(|key| {
+            let ordering = compare_values(&key.column.value(a), &key.column.value(b));
+            if key.descending { ordering.reverse() } else { ordering }
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+pub fn sort_records(records: &mut [Record], keys: &[SortKey]) {
+    records.sort_by(|a, b| compare_records(a, b, keys));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    Lt,
+    Gt,
+    Contains,
+}
+
+// 过滤条件，如 colo=SJC、latency_ms<300、status!=403、cdn~cloud
+#[derive(Clone, Debug)]
+pub struct Filter {
+    column: Column,
+    operator: Operator,
+    value: String,
+}
+
+// 两个字符的运算符要放在前面
+static OPERATORS: &[(&str, Operator)] = &[
+    ("!=", Operator::Ne),
+    ("<=", Operator::Le),
+    (">=", Operator::Ge),
+    ("=", Operator::Eq),
+    ("<", Operator::Lt),
+    (">", Operator::Gt),
+    ("~", Operator::Contains),
+];
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pos, symbol, operator) = OPERATORS.iter()
+            .filter_map(|(symbol, operator)| s.find(symbol).map(|pos| (pos, *symbol, *operator)))
+            .min_by_key(|(pos, symbol, _)| (*pos, usize::MAX - symbol.len()))
+            .ok_or_else(|| format!("{}: {}", t("无效的过滤条件", "invalid filter"), s))?;
+        Ok(Filter {
+            column: s[..pos].parse()?,
+            operator,
+            value: s[pos + symbol.len()..].trim().to_string(),
+        })
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, record: &Record) -> bool {
+        let value = self.column.value(record);
+        let ordering = compare_values(&value, &self.value);
+        match self.operator {
+            Operator::Eq => ordering == Ordering::Equal,
+            Operator::Ne => ordering != Ordering::Equal,
+            Operator::Le => ordering != Ordering::Greater,
+            Operator::Ge => ordering != Ordering::Less,
+            Operator::Lt => ordering == Ordering::Less,
+            Operator::Gt => ordering == Ordering::Greater,
+            Operator::Contains => value.contains(&self.value),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::utils::i18n::{ arg_value, parse_lang, t, Lang };
+
+use clap::{ parser::ValueSource, ArgMatches, Command };
+use log::warn;
+use std::{ error::Error, fs, path::PathBuf };
+use toml::{ Table, Value };
+
+// 配置文件的名称，先在当前目录中查找，再到用户的配置目录中查找
+static CONFIG_FILE: &str = "config.toml";
+static CONFIG_DIR: &str = "curl_cdn-cgi_trace_rust";
+
+// 不能写在配置文件中的参数
+static SKIPPED: &[&str] = &["config", "profile", "help", "version"];
+
+// 查找配置文件：--config指定的文件、当前目录的config.toml、用户配置目录中的config.toml
+pub fn find_config(explicit: Option<&str>) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(PathBuf::from(path));
+    }
+    let local = PathBuf::from(CONFIG_FILE);
+    if local.exists() {
+        return Some(local);
+    }
+    let user = dirs::config_dir()?.join(CONFIG_DIR).join(CONFIG_FILE);
+    user.exists().then_some(user)
+}
+
+// 读取配置文件中的参数：顶层的值是默认值，指定了--profile就用[profile.名称]中的值覆盖
+pub fn load_values(path: &PathBuf, profile: Option<&str>) -> Result<Table, Box<dyn Error>> {
+    let mut table: Table = toml::from_str(&fs::read_to_string(path)?)?;
+    let profiles = table.remove("profile");
+    let Some(name) = profile else {
+        return Ok(table);
+    };
+    let selected = profiles
+        .as_ref()
+        .and_then(|profiles| profiles.get(name))
+        .and_then(|profile| profile.as_table())
+        .ok_or_else(|| format!("{}: {}", t("配置文件中没有这个profile", "profile not found in the config file"), name))?;
+    for (key, value) in selected {
+        table.insert(key.clone(), value.clone());
+    }
+    Ok(table)
+}
+
+// 配置文件(和--profile)中的lang，在clap解析之前确定语言时使用，配置文件的错误留给之后解析参数时报告；
+// 这里不能用load_values，profile不存在时它生成的错误信息会在确定语言之前固定下语言
+pub fn lang_from_config() -> Option<Lang> {
+    let path = find_config(arg_value("config").as_deref())?;
+    let table: Table = toml::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let profile = arg_value("profile").and_then(|name| table.get("profile")?.get(name.as_str())?.get("lang").cloned());
+    profile.as_ref().or_else(|| table.get("lang"))?.as_str().and_then(parse_lang)
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// 把配置文件中的值转换成selected这个(子)命令的参数，命令行中已经指定的参数不转换，这样命令行的参数优先，
+// root需要先build，全局参数才会传递到子命令中
+pub fn config_args(root: &Command, selected: &Command, matches: &ArgMatches, values: &Table) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    for (key, value) in values {
+        let id = key.replace('-', "_");
+        if SKIPPED.contains(&id.as_str()) {
+            continue;
+        }
+        let Some(arg) = selected.get_arguments().find(|arg| arg.get_id() == id.as_str()) else {
+            // 其它子命令的参数，这次用不到
+            let known = root
+                .get_arguments()
+                .chain(root.get_subcommands().flat_map(|sub| sub.get_arguments()))
+                .any(|arg| arg.get_id() == id.as_str());
+            if !known {
+                warn!("{}: {}", t("配置文件中未知的参数", "unknown key in the config file"), key);
+            }
+            continue;
+        };
+        if matches.value_source(&id) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        // 与命令行参数冲突的值也不转换，如命令行的--append优先于配置文件中的merge
+        let conflicted = selected
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .any(|other| matches.value_source(other.get_id().as_str()) == Some(ValueSource::CommandLine));
+        if conflicted {
+            continue;
+        }
+        let flag = match (arg.get_long(), arg.get_short()) {
+            (Some(long), _) => format!("--{}", long),
+            (None, Some(short)) => format!("-{}", short),
+            (None, None) => {
+                continue;
+            }
+        };
+        match value {
+            Value::Boolean(true) => args.push(flag),
+            Value::Boolean(false) => {}
+            Value::Array(items) => {
+                for item in items {
+                    args.push(format!("{}={}", flag, value_to_string(item)));
+                }
+            }
+            other => args.push(format!("{}={}", flag, value_to_string(other))),
+        }
+    }
+    args
+}
+
+// 把配置文件中的值插入到命令行参数argv中：没有子命令时插入到程序名称后面，有子命令时插入到子命令后面，
+// matches是argv解析的结果，root需要先build
+pub fn splice_config_args(root: &Command, matches: &ArgMatches, mut argv: Vec<String>, values: &Table) -> Vec<String> {
+    let (selected, selected_matches, position) = match matches.subcommand() {
+        Some((name, sub_matches)) => {
+            let position = argv
+                .iter()
+                .skip(1)
+                .position(|arg| arg == name)
+                .map_or(argv.len(), |i| i + 2);
+            (root.find_subcommand(name).cloned().unwrap_or_default(), sub_matches, position)
+        }
+        None => (root.clone(), matches, 1),
+    };
+    let extra = config_args(root, &selected, selected_matches, values);
+    argv.splice(position..position, extra);
+    argv
+}
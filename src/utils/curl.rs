@@ -1,27 +1,37 @@
+use crate::utils::cdn::{ detect, CdnDetector };
+use crate::utils::dns::split_port;
+use crate::utils::fingerprint::Rule;
+use crate::utils::i18n::t;
 use crate::utils::models::Airport;
 use crate::utils::models::Record;
+use crate::utils::models::Target;
+use crate::utils::prober::{ ProbeRequest, Prober };
+use crate::utils::timeout::TimeoutPolicy;
 
 use ipnetwork::IpNetwork;
 use log::{ info, warn };
-use std::{ io::{ self, Write }, process::{ Command, Stdio }, time::Instant };
+use std::{ io::{ self, Write }, net::IpAddr, process::Command, time::Duration };
 use url::Url;
 
 // 检查curl是否已安装，没有就退出程序
 pub fn check_curl_installed() {
-    if !Command::new("curl").arg("--version").output().is_ok() {
-        print!("电脑中，没有安装有curl命令！按Enter键退出程序！");
+    if Command::new("curl").arg("--version").output().is_err() {
+        print!(
+            "{}",
+            t(
+                "电脑中，没有安装有curl命令！按Enter键退出程序！",
+                "curl is not installed! Press Enter to exit."
+            )
+        );
         io::stdout().flush().expect("Failed to flush stdout");
         let _ = io::stdin().read_line(&mut String::new());
         std::process::exit(1);
     }
 }
 
-pub fn run_command_and_process_data(
-    ip: &str,
-    airports: Vec<Airport>,
-    jetbrains: bool
-) -> Result<Record, io::Error> {
-    let formatted_ip = if let Ok(ip_network) = ip.parse::<IpNetwork>() {
+// IPv6地址加上方括号，去掉末尾的"/"
+fn format_addr(ip: &str) -> String {
+    if let Ok(ip_network) = ip.parse::<IpNetwork>() {
         if ip_network.is_ipv6() { format!("[{}]", ip) } else { ip_network.ip().to_string() }
     } else {
         let trimmed_ip = if ip.ends_with('/') {
@@ -30,7 +40,18 @@ pub fn run_command_and_process_data(
             ip.to_string()
         };
         trimmed_ip
-    };
+    }
+}
+
+pub fn run_command_and_process_data(
+    target: &Target,
+    airports: &[Airport],
+    detectors: &[Box<dyn CdnDetector>],
+    prober: &dyn Prober,
+    timeouts: &TimeoutPolicy
+) -> Result<Record, io::Error> {
+    let ip = target.addr.as_str();
+    let formatted_ip = format_addr(ip);
     let host_name = if formatted_ip.starts_with("http://") || formatted_ip.starts_with("https://") {
         let url_parse = Url::parse(&formatted_ip).unwrap(); // 解析URL
         let domain = url_parse.host_str().unwrap_or_default().to_string(); // 提取域名
@@ -38,90 +59,185 @@ pub fn run_command_and_process_data(
     } else {
         formatted_ip
     };
-    let url: String = match jetbrains {
-        true => ip.to_string(),
-        false => format!("http://{}/cdn-cgi/trace", host_name),
+    // 域名解析后的目标，使用域名作为Host/SNI，并固定连接到解析出来的IP，原地址带端口的保留端口
+    let (url, resolve) = match (&target.host, &target.resolved_ip) {
+        (Some(host), Some(resolved_ip)) => {
+            let authority = match (ip.contains("://"), split_port(ip).1) {
+                (false, Some(port)) => format!("{}:{}", host, port),
+                _ => host.clone(),
+            };
+            (format!("http://{}/cdn-cgi/trace", authority), Some(resolved_ip.as_str()))
+        }
+        _ => (format!("http://{}/cdn-cgi/trace", host_name), None),
+    };
+    // 需要校验trace中h=字段的，就要获取响应体，不能只请求响应头
+    let method = if target.verify_host { "GET" } else { "HEAD" };
+    let domain = target.host.clone().unwrap_or_default();
+    let resolved_ip = target.resolved_ip.clone().unwrap_or_default();
+    // 日志中显示的地址，域名目标附带解析出来的IP
+    let display = match (&target.host, &target.resolved_ip) {
+        (Some(host), Some(resolved_ip)) if resolved_ip == ip => format!("{} [{}]", ip, host),
+        (_, Some(resolved_ip)) => format!("{} -> {}", ip, resolved_ip),
+        _ => ip.to_string(),
     };
-    let start_time = Instant::now(); // 开始时间
-
-    let curl_process = Command::new("curl")
-        .args(["/dev/null", "-I", &url, "-s", "--connect-timeout", "3", "--max-time", "10"])
-        .stdout(Stdio::piped())
-        .spawn();
-
-    // 检查curl进程是否成功启动
-    match curl_process {
-        Ok(child) => {
-            let output = child.wait_with_output()?; // 等待子进程完成
-            let elapsed_duration = start_time.elapsed(); // 结束时间
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let lines: Vec<&str> = stdout.lines().collect();
-            let mut status_code = String::new();
-            for line in lines {
-                if line.starts_with("HTTP/1.1") {
-                    let parts: Vec<&str> = line.split(' ').collect();
-                    if parts.len() >= 2 {
-                        status_code = parts[1].to_string();
+    match prober.request(&(ProbeRequest { url: &url, method, resolve, timeouts })) {
+        Ok(response) => {
+            let elapsed_duration = response.elapsed;
+            // trace响应体中的h=字段，是CloudFlare收到的Host
+            let trace_host = response.body
+                .lines()
+                .find_map(|line| line.strip_prefix("h="))
+                .map(|host| host.to_string());
+            // 没有HTTP响应的，不做识别（IP段匹配不代表这个地址可用）
+            if let Some(status_code) = response.status_code {
+                let headers = response.headers;
+                let connect_ip = match &target.resolved_ip {
+                    Some(resolved_ip) => resolved_ip.parse::<IpAddr>().ok(),
+                    None => split_port(&host_name).0.parse::<IpAddr>().ok(),
+                };
+                if let Some(detection) = detect(detectors, &headers, connect_ip) {
+                    // 优选域名模式，h=字段必须是指定的域名，才说明这个IP能为该域名提供服务
+                    if target.verify_host && trace_host.as_deref() != target.host.as_deref() {
+                        let reason = t("trace中的h=与指定的域名不一致", "trace h= does not match the host");
+                        warn!("{} | {}: h={}", display, reason, trace_host.unwrap_or_default());
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
                     }
-                } else if jetbrains == false && line.starts_with("CF-RAY:") {
-                    if let Some(pos) = line.rfind('-') {
-                        // 获取 `"-"` 后面的部分
-                        let colo = &line[pos + 1..]; // +1 是为了跳过 `"-"` 字符
-                        match airports.iter().find(|a| a.iata == colo) {
-                            Some(airport) => {
-                                let record = Record {
-                                    ip: ip.to_string(),
-                                    colo: colo.to_string(),
-                                    country: airport.cca2.clone(),
-                                    region: airport.region.clone(),
-                                    city: airport.city.clone(),
-                                    delay: elapsed_duration,
-                                    http_status_code: status_code,
-                                    is_jetbrains: false, // 这里没有扫描，不代表不是JetBrains的许可证服务器
-                                };
-                                info!(
-                                    "{} | {} | {} | {} | {} | {} ms",
-                                    ip,
-                                    colo,
-                                    airport.cca2,
-                                    airport.region,
-                                    airport.city,
-                                    elapsed_duration.as_millis()
-                                );
-                                return Ok(record);
-                            }
-                            None => {}
-                        }
-                    }
-                } else if
-                    jetbrains == true &&
-                    line.starts_with("Location: https://account.jetbrains.com/fls-auth")
-                {
+                    let colo = detection.pop;
+                    let airport = airports.iter().find(|a| a.iata == colo);
                     let record = Record {
                         ip: ip.to_string(),
-                        colo: "".to_string(),
-                        country: "".to_string(),
-                        region: "".to_string(),
-                        city: "".to_string(),
+                        domain,
+                        resolved_ip,
+                        colo,
+                        country: airport.map(|a| a.cca2.clone()).unwrap_or_default(),
+                        region: airport.map(|a| a.region.clone()).unwrap_or_default(),
+                        city: airport.map(|a| a.city.clone()).unwrap_or_default(),
                         delay: elapsed_duration,
                         http_status_code: status_code,
-                        is_jetbrains: true,
+                        fingerprints: vec![],
+                        license_server: None,
+                        cdn: detection.cdn.to_string(),
+                        detection: detection.signals.join("+"),
+                        scanned_at: "".to_string(),
                     };
-                    info!("{} | JetBrains License server | {}", ip, elapsed_duration.as_millis());
+                    info!(
+                        "{} | {} | {} | {} | {} | {} | {} ms | {}",
+                        display,
+                        record.cdn,
+                        record.colo,
+                        record.country,
+                        record.region,
+                        record.city,
+                        elapsed_duration.as_millis(),
+                        record.detection
+                    );
                     return Ok(record);
                 }
             }
             // 都不符合条件的情况
-            if jetbrains {
-                warn!("{} | 连接失败/超时，响应头中，找不到jetbrains相关的fls-auth信息！", ip);
-            } else {
-                warn!("{} | 连接失败/超时，响应头中，找不到CloudFlare相关的信息！", ip);
+            let reason = t(
+                "连接失败/超时，响应头中，找不到CDN相关的信息！",
+                "connection failed/timed out, or no CDN signature in the response headers"
+            );
+            warn!("{} | {}", display, reason);
+            Err(io::Error::new(io::ErrorKind::InvalidInput, reason))
+        }
+        Err(e) => {
+            let reason = t("探测请求失败", "probe failed");
+            warn!("{} | {}: {}", display, reason, e);
+            Err(io::Error::new(io::ErrorKind::InvalidInput, reason))
+        }
+    }
+}
+
+// curl请求的响应
+pub struct HttpResponse {
+    pub status_code: Option<String>,
+    pub headers: Vec<(String, String)>, // 名称是小写的
+    pub body: String,
+    pub elapsed: Duration,
+}
+
+impl HttpResponse {
+    pub fn status(&self) -> Option<u16> {
+        self.status_code.as_deref().and_then(|code| code.parse::<u16>().ok())
+    }
+}
+
+// 拼接请求地址，原地址是URL的就替换它的路径和查询参数
+pub fn build_url(addr: &str, path_and_query: &str) -> Option<String> {
+    let formatted_ip = format_addr(addr);
+    if formatted_ip.starts_with("http://") || formatted_ip.starts_with("https://") {
+        let url = Url::parse(&formatted_ip).ok()?;
+        let origin = url.origin().ascii_serialization();
+        Some(format!("{}{}", origin, path_and_query))
+    } else {
+        Some(format!("http://{}{}", formatted_ip, path_and_query))
+    }
+}
+
+// 发送请求，不跟随重定向
+pub fn http_request(
+    prober: &dyn Prober,
+    timeouts: &TimeoutPolicy,
+    url: &str,
+    method: &str
+) -> Result<HttpResponse, io::Error> {
+    prober.request(&(ProbeRequest { url, method, resolve: None, timeouts }))
+}
+
+// 指纹识别：按规则逐个请求，返回匹配到的规则名称，延迟和状态码取第一个匹配的规则
+pub fn run_fingerprint_rules(
+    target: &Target,
+    rules: &[Rule],
+    prober: &dyn Prober,
+    timeouts: &TimeoutPolicy
+) -> Result<Record, io::Error> {
+    let ip = target.addr.as_str();
+    let mut matched: Vec<String> = Vec::new();
+    let mut first_hit: Option<(String, Duration)> = None;
+    let mut responded = false;
+    for rule in rules {
+        let Some(url) = build_url(ip, &rule.path) else {
+            continue;
+        };
+        let response = match http_request(prober, timeouts, &url, &rule.method) {
+            Ok(response) => response,
+            Err(e) => {
+                let reason = t("探测请求失败", "probe failed");
+                warn!("{} | {}: {}", ip, reason, e);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, reason));
             }
-            return Err(io::Error::new(io::ErrorKind::InvalidInput, "未知错误！"));
+        };
+        responded |= response.status_code.is_some();
+        if rule.matches(response.status(), &response.headers, &response.body) {
+            matched.push(rule.name.clone());
+            if first_hit.is_none() {
+                first_hit = Some((response.status_code.unwrap_or_default(), response.elapsed));
+            }
+        }
+    }
+    match first_hit {
+        Some((http_status_code, delay)) => {
+            info!("{} | {} | {}", ip, matched.join("+"), delay.as_millis());
+            Ok(Record {
+                ip: ip.to_string(),
+                delay,
+                http_status_code,
+                fingerprints: matched,
+                ..Record::default()
+            })
+        }
+        // 没有收到任何HTTP响应时返回NotConnected，和有响应但没有匹配的地址区分开
+        None if !responded => {
+            let reason = t("连接失败/超时！", "connection failed/timed out");
+            warn!("{} | {}", ip, reason);
+            Err(io::Error::new(io::ErrorKind::NotConnected, reason))
         }
-        Err(_e) => {
-            warn!("{} | CURL启动失败", ip);
-            return Err(io::Error::new(io::ErrorKind::InvalidInput, "未知错误！"));
+        None => {
+            let reason = t("没有匹配到任何指纹规则！", "no fingerprint rule matched");
+            warn!("{} | {}", ip, reason);
+            Err(io::Error::new(io::ErrorKind::InvalidInput, reason))
         }
     }
 }
@@ -0,0 +1,168 @@
+use crate::utils::files::ResultSink;
+use crate::utils::i18n::t;
+use crate::utils::models::Record;
+
+use rusqlite::{ params, Connection };
+use std::error::Error;
+
+// 当前的本地时间
+fn now() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// 打开数据库，不存在的表就创建
+fn open_db(path: &str) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            args TEXT NOT NULL,
+            seed TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT
+        );
+        CREATE TABLE IF NOT EXISTS records (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id INTEGER NOT NULL REFERENCES runs(id),
+            ip TEXT NOT NULL,
+            domain TEXT NOT NULL,
+            resolved_ip TEXT NOT NULL,
+            colo TEXT NOT NULL,
+            country TEXT NOT NULL,
+            region TEXT NOT NULL,
+            city TEXT NOT NULL,
+            latency_ms INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            cdn TEXT NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS records_ip ON records(ip);"
+    )?;
+    Ok(conn)
+}
+
+// 每个事务最多写入的结果数量，程序意外退出时最多丢失这么多条
+const BATCH_SIZE: usize = 500;
+
+// SQLite：每次扫描记录一条runs，每条结果都关联到这次扫描的run_id，
+// 结果在事务中分批写入，不用每条结果都提交一次
+pub struct SqliteSink {
+    path: String,
+    args: String,
+    seed: u64,
+    conn: Option<Connection>,
+    run_id: i64,
+    pending: usize, // 当前事务中还没有提交的结果数量
+}
+
+impl SqliteSink {
+    pub fn new(path: &str, args: String, seed: u64) -> Self {
+        SqliteSink { path: path.to_string(), args, seed, conn: None, run_id: 0, pending: 0 }
+    }
+}
+
+impl ResultSink for SqliteSink {
+    fn open(&mut self) -> Result<(), Box<dyn Error>> {
+        let conn = open_db(&self.path)?;
+        conn.execute(
+            "INSERT INTO runs (args, seed, started_at) VALUES (?1, ?2, ?3)",
+            params![self.args, self.seed.to_string(), now()]
+        )?;
+        self.run_id = conn.last_insert_rowid();
+        conn.execute_batch("BEGIN")?;
+        self.conn = Some(conn);
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), Box<dyn Error>> {
+        if let Some(conn) = &self.conn {
+            conn.prepare_cached(
+                "INSERT INTO records (run_id, ip, domain, resolved_ip, colo, country, region, city, latency_ms, status, cdn, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+            )?.execute(
+                params![
+                    self.run_id,
+                    record.ip,
+                    record.domain,
+                    record.resolved_ip,
+                    record.colo,
+                    record.country,
+                    record.region,
+                    record.city,
+                    record.delay.as_millis() as i64,
+                    record.http_status_code,
+                    record.cdn,
+                    serde_json::to_string(record)?
+                ]
+            )?;
+            self.pending += 1;
+            if self.pending == BATCH_SIZE {
+                conn.execute_batch("COMMIT; BEGIN")?;
+                self.pending = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(conn) = self.conn.take() {
+            conn.execute(
+                "UPDATE runs SET finished_at = ?1 WHERE id = ?2",
+                params![now(), self.run_id]
+            )?;
+            conn.execute_batch("COMMIT")?;
+        }
+        Ok(())
+    }
+}
+
+// 打印一个地址在历次扫描中的延迟和数据中心
+pub fn print_history(path: &str, ip: &str) -> Result<(), Box<dyn Error>> {
+    let conn = open_db(path)?;
+    let mut stmt = conn.prepare(
+        "SELECT runs.id, runs.started_at, records.colo, records.country, records.city, records.latency_ms, records.status
+         FROM records JOIN runs ON runs.id = records.run_id
+         WHERE records.ip = ?1 OR records.resolved_ip = ?1
+         ORDER BY runs.id"
+    )?;
+    let rows = stmt.query_map(params![ip], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, i64>(5)?,
+            row.get::<_, String>(6)?,
+        ))
+    })?;
+    println!(
+        "{:<6} {:<20} {:<6} {:<8} {:<20} {:>10} {:>6}",
+        "run",
+        t("扫描时间", "time"),
+        "colo",
+        "alpha-2",
+        t("城市", "city"),
+        "latency_ms",
+        "status"
+    );
+    let mut count = 0;
+    for row in rows {
+        let (run_id, started_at, colo, country, city, latency_ms, status) = row?;
+        println!(
+            "{:<6} {:<20} {:<6} {:<8} {:<20} {:>10} {:>6}",
+            run_id,
+            started_at,
+            colo,
+            country,
+            city,
+            latency_ms,
+            status
+        );
+        count += 1;
+    }
+    if count == 0 {
+        println!("{}: {}", t("没有这个地址的扫描记录", "no scan history for"), ip);
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use crate::utils::i18n::t;
+use crate::utils::models::Record;
+
+use std::collections::BTreeMap;
+
+fn display(record: &Record) -> String {
+    match record.resolved_ip.is_empty() || record.resolved_ip == record.ip {
+        true => record.ip.clone(),
+        false => format!("{} -> {}", record.ip, record.resolved_ip),
+    }
+}
+
+// 比较两次扫描的结果：新出现的、消失的、数据中心变化的、延迟变化超过阈值的
+pub fn print_diff(old: Vec<Record>, new: Vec<Record>, threshold_ms: u64) {
+    let old: BTreeMap<(String, String), Record> = old
+        .into_iter()
+        .map(|record| (record.key(), record))
+        .collect();
+    let new: BTreeMap<(String, String), Record> = new
+        .into_iter()
+        .map(|record| (record.key(), record))
+        .collect();
+
+    let (mut appeared, mut disappeared, mut changed) = (0, 0, 0);
+    for (key, record) in new.iter() {
+        match old.get(key) {
+            None => {
+                println!(
+                    "+ {} | {} | {} ms",
+                    display(record),
+                    record.colo,
+                    record.delay.as_millis()
+                );
+                appeared += 1;
+            }
+            Some(before) => {
+                let mut changes: Vec<String> = Vec::new();
+                if before.colo != record.colo {
+                    changes.push(format!("colo {} -> {}", before.colo, record.colo));
+                }
+                let (a, b) = (before.delay.as_millis() as i64, record.delay.as_millis() as i64);
+                if (b - a).unsigned_abs() > threshold_ms {
+                    changes.push(format!("{} ms -> {} ms ({:+})", a, b, b - a));
+                }
+                if !changes.is_empty() {
+                    println!("~ {} | {}", display(record), changes.join(" | "));
+                    changed += 1;
+                }
+            }
+        }
+    }
+    for (key, record) in old.iter() {
+        if !new.contains_key(key) {
+            println!("- {} | {} | {} ms", display(record), record.colo, record.delay.as_millis());
+            disappeared += 1;
+        }
+    }
+    println!(
+        "\n{}: +{} -{} ~{}",
+        t("新出现/消失/变化的地址数量", "appeared/disappeared/changed"),
+        appeared,
+        disappeared,
+        changed
+    );
+}
@@ -0,0 +1,145 @@
+use crate::utils::i18n::t;
+use crate::utils::models::Target;
+
+use hickory_resolver::{
+    config::{ LookupIpStrategy, NameServerConfigGroup, ResolverConfig, ResolverOpts },
+    TokioAsyncResolver,
+};
+use ipnetwork::IpNetwork;
+use log::warn;
+use std::{ net::{ IpAddr, SocketAddr }, sync::Arc };
+use tokio::{ sync::Semaphore, task::JoinSet };
+use url::Url;
+
+// 拆分地址末尾的端口，支持"1.2.3.4:8080"、"[2001:db8::1]:8080"、"example.com:8080"，
+// 没有端口(包括不带方括号的IPv6地址)时返回None，返回的主机不带方括号
+pub fn split_port(addr: &str) -> (String, Option<u16>) {
+    let addr = addr.trim_end_matches('/');
+    if let Ok(socket) = addr.parse::<SocketAddr>() {
+        return (socket.ip().to_string(), Some(socket.port()));
+    }
+    if let Some((host, port)) = addr.rsplit_once(':') {
+        if !host.contains(':') {
+            if let Ok(port) = port.parse::<u16>() {
+                return (host.to_string(), Some(port));
+            }
+        }
+    }
+    (addr.trim_start_matches('[').trim_end_matches(']').to_string(), None)
+}
+
+// 从地址中提取需要DNS解析的域名，IP、CIDR、IP:端口返回None，域名:端口只返回域名
+pub fn extract_domain(addr: &str) -> Option<String> {
+    if addr.parse::<IpNetwork>().is_ok() || addr.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    let host = if addr.starts_with("http://") || addr.starts_with("https://") {
+        Url::parse(addr).ok()?.host_str()?.to_string()
+    } else {
+        split_port(addr).0
+    };
+    // URL中的IPv6地址带有方括号
+    let bare = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() || bare.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    Some(host)
+}
+
+// 创建DNS解析器，没有指定DNS服务器时，使用系统的DNS配置
+fn build_resolver(nameserver: Option<SocketAddr>) -> TokioAsyncResolver {
+    let mut opts = ResolverOpts::default();
+    opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6; // 同时查询A和AAAA记录
+    match nameserver {
+        Some(addr) => {
+            let group = NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true);
+            TokioAsyncResolver::tokio(ResolverConfig::from_parts(None, vec![], group), opts)
+        }
+        None =>
+            match hickory_resolver::system_conf::read_system_conf() {
+                Ok((config, _)) => TokioAsyncResolver::tokio(config, opts),
+                Err(_) => TokioAsyncResolver::tokio(ResolverConfig::default(), opts),
+            }
+    }
+}
+
+// 解析DNS服务器地址，支持"1.1.1.1"、"1.1.1.1:53"、"[2606:4700::1111]:53"的写法
+pub fn parse_nameserver(s: &str) -> Result<SocketAddr, String> {
+    if let Ok(addr) = s.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    match s.parse::<IpAddr>() {
+        Ok(ip) => Ok(SocketAddr::new(ip, 53)),
+        Err(_) => Err(format!("{}: {}", t("无效的DNS服务器地址", "invalid DNS server address"), s)),
+    }
+}
+
+// 域名解析阶段：把域名解析成全部的A/AAAA记录，每个IP都作为一个目标，并以域名作为Host/SNI，
+// 目标的顺序与输入的顺序相同(同一个域名的IP按地址排序)，这样相同的--seed得到相同的扫描顺序，
+// 同时进行的解析数量是concurrency(--pool)
+pub async fn resolve_targets(addrs: Vec<String>, nameserver: Option<SocketAddr>, concurrency: usize) -> Vec<Target> {
+    let resolver = build_resolver(nameserver);
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut resolved: Vec<Vec<Target>> = (0..addrs.len()).map(|_| Vec::new()).collect();
+    let mut tasks = JoinSet::new();
+    for (index, addr) in addrs.into_iter().enumerate() {
+        match extract_domain(&addr) {
+            Some(domain) => {
+                let resolver = resolver.clone();
+                let semaphore = Arc::clone(&semaphore);
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    let result = resolver.lookup_ip(domain.as_str()).await;
+                    (index, addr, domain, result)
+                });
+            }
+            None => resolved[index].push(Target::from(addr)),
+        }
+    }
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((index, addr, domain, result)) = joined else {
+            continue;
+        };
+        match result {
+            Ok(lookup) => {
+                let mut ips: Vec<IpAddr> = lookup.iter().collect();
+                ips.sort();
+                ips.dedup();
+                resolved[index] = ips
+                    .into_iter()
+                    .map(|ip| Target {
+                        addr: addr.clone(),
+                        host: Some(domain.clone()),
+                        resolved_ip: Some(ip.to_string()),
+                        verify_host: false,
+                    })
+                    .collect();
+            }
+            Err(e) => warn!("{} | {}: {}", domain, t("DNS解析失败", "DNS resolution failed"), e),
+        }
+    }
+    resolved.into_iter().flatten().collect()
+}
+
+// 优选域名模式：每个IP都以指定的域名作为Host/SNI去请求，并校验trace中的h=字段
+pub fn pin_targets_to_host(addrs: Vec<String>, host: &str) -> Vec<Target> {
+    let mut targets: Vec<Target> = Vec::new();
+    for addr in addrs {
+        match addr.parse::<IpAddr>() {
+            Ok(ip) =>
+                targets.push(Target {
+                    addr: ip.to_string(),
+                    host: Some(host.to_string()),
+                    resolved_ip: Some(ip.to_string()),
+                    verify_host: true,
+                }),
+            Err(_) =>
+                warn!(
+                    "{} | {}",
+                    addr,
+                    t("不是IP地址，优选域名模式下跳过", "not an IP address, skipped in --host mode")
+                ),
+        }
+    }
+    targets
+}
@@ -1,13 +1,48 @@
-use std::{ collections::HashSet, error::Error, fs::File, io::{ self, BufRead, Write }, path::Path };
+use crate::utils::columns::{ compare_records, sort_records, Column, SortKey };
+use crate::utils::i18n::t;
+use crate::utils::models::Record;
+
+use std::{
+    cmp::Ordering,
+    collections::{ HashMap, HashSet },
+    error::Error,
+    fs::{ self, File, OpenOptions },
+    io::{ self, BufRead, BufWriter, Read, Seek, SeekFrom, Write },
+    path::Path,
+};
 use csv::Writer;
 
+// 结果输出的格式
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv,
+    Json,
+    Jsonl,
+}
+
+// 合并结果文件时，同一个地址保留哪一条记录
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeKeep {
+    Newest, // 本次扫描的结果
+    Best, // 按排序的列比较，排在前面的结果
+}
+
+// 已经存在的结果文件怎么处理
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteMode {
+    Overwrite,
+    Append, // 保留原有的记录，本次的结果追加在后面
+    Resume, // 继续中断的扫描：和追加一样，但是沿用输出文件中已有的列
+    Merge(MergeKeep), // 按地址去重，同一个地址只保留一条记录
+}
+
 pub fn read_text_file<P>(filename: P) -> io::Result<Vec<String>> where P: AsRef<Path> {
     let file = match File::open(&filename) {
         Ok(file) => file,
         Err(e) => {
             let filename_str = filename.as_ref().to_str().unwrap_or("<invalid path>");
-            println!("打开{}文件失败，错误原因是:{}", filename_str, e);
-            print!("按Enter键退出程序！");
+            println!("{} {}: {}", t("打开文件失败", "Failed to open"), filename_str, e);
+            print!("{}", t("按Enter键退出程序！", "Press Enter to exit."));
             io::stdout().flush().expect("Failed to flush stdout");
             let _ = io::stdin().read_line(&mut String::new());
             std::process::exit(1);
@@ -40,3 +75,383 @@ pub fn write_to_csv(csv_file: &str, records: Vec<Vec<String>>) -> Result<(), Box
     wtr.flush()?;
     Ok(())
 }
+
+// 输出为JSON数组
+pub fn write_to_json(json_file: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = BufWriter::new(File::create(json_file)?);
+    serde_json::to_writer_pretty(&mut wtr, records)?;
+    wtr.flush()?;
+    Ok(())
+}
+
+// 输出为JSON Lines，每行一条记录
+pub fn write_to_jsonl(jsonl_file: &str, records: &[Record]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = BufWriter::new(File::create(jsonl_file)?);
+    for record in records {
+        serde_json::to_writer(&mut wtr, record)?;
+        wtr.write_all(b"\n")?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// 读取之前输出的结果文件，支持CSV（中文或英文标题、任意列）、JSON数组和JSON Lines
+pub fn read_records(path: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') {
+        return Ok(serde_json::from_str(trimmed)?);
+    }
+    if trimmed.starts_with('{') {
+        let mut records = Vec::new();
+        for line in trimmed.lines().filter(|line| !line.trim().is_empty()) {
+            records.push(serde_json::from_str(line)?);
+        }
+        return Ok(records);
+    }
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
+    let columns: Vec<Option<Column>> = rdr
+        .headers()?
+        .iter()
+        .map(Column::from_header)
+        .collect();
+    let mut records = Vec::new();
+    for row in rdr.records() {
+        let row = row?;
+        let mut record = Record::default();
+        for (column, value) in columns.iter().zip(row.iter()) {
+            if let Some(column) = column {
+                column.set(&mut record, value);
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// 把本次的结果和已有的结果文件合并成最终要写入的记录，文件不存在就只有本次的结果
+fn combine_with_existing(
+    path: &str,
+    mode: WriteMode,
+    mut records: Vec<Record>,
+    sort_by: &[SortKey]
+) -> Result<Vec<Record>, Box<dyn Error>> {
+    sort_records(&mut records, sort_by);
+    if mode == WriteMode::Overwrite || !Path::new(path).exists() {
+        return Ok(records);
+    }
+    let mut existing = read_records(path)?;
+    match mode {
+        WriteMode::Append | WriteMode::Resume => {
+            existing.extend(records);
+            Ok(existing)
+        }
+        WriteMode::Merge(keep) => {
+            let mut index: HashMap<(String, String), usize> = existing
+                .iter()
+                .enumerate()
+                .map(|(i, record)| (record.key(), i))
+                .collect();
+            for record in records {
+                match index.get(&record.key()) {
+                    Some(&i) => {
+                        let replace = match keep {
+                            MergeKeep::Newest => true,
+                            MergeKeep::Best =>
+                                compare_records(&record, &existing[i], sort_by) == Ordering::Less,
+                        };
+                        if replace {
+                            existing[i] = record;
+                        }
+                    }
+                    None => {
+                        index.insert(record.key(), existing.len());
+                        existing.push(record);
+                    }
+                }
+            }
+            sort_records(&mut existing, sort_by);
+            Ok(existing)
+        }
+        WriteMode::Overwrite => Ok(records),
+    }
+}
+
+// 结果的输出端：扫描开始时open，每得到一条结果就write_record，扫描结束后finish
+pub trait ResultSink {
+    fn open(&mut self) -> Result<(), Box<dyn Error>>;
+    fn write_record(&mut self, record: &Record) -> Result<(), Box<dyn Error>>;
+    fn finish(&mut self) -> Result<(), Box<dyn Error>>;
+}
+
+// 扫描过程中实时写入的临时文件，程序中断时，已经扫描到的结果保存在这里
+fn partial_path(path: &str) -> String {
+    format!("{}.part", path)
+}
+
+// CSV的标题行
+fn csv_header(columns: &[Column]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| column.header().to_string())
+        .collect()
+}
+
+// 把Record转换成CSV的行
+fn csv_row(record: &Record, columns: &[Column]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| column.value(record))
+        .collect()
+}
+
+// 追加到已有的CSV前文件的状态
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CsvAppend {
+    Empty, // 文件不存在或者是空文件，需要先写入标题行
+    Matching, // 列和本次输出的列一致
+    MissingScannedAt(usize), // 只缺少扫描时间列(没有--append时输出的文件)，需要先在这个位置加上这一列
+}
+
+// 追加到已有的CSV时，文件的列必须和本次输出的列一致(中文、英文标题都可以)，
+// 或者只缺少扫描时间列，否则拒绝追加
+fn check_csv_append(path: &str, columns: &[Column]) -> Result<CsvAppend, Box<dyn Error>> {
+    if fs::metadata(path).map_or(true, |metadata| metadata.len() == 0) {
+        return Ok(CsvAppend::Empty);
+    }
+    let mut rdr = csv::Reader::from_path(path)?;
+    let existing: Vec<Option<Column>> = rdr.headers()?.iter().map(Column::from_header).collect();
+    if existing == columns.iter().copied().map(Some).collect::<Vec<_>>() {
+        return Ok(CsvAppend::Matching);
+    }
+    let position = columns.iter().position(|column| *column == Column::ScannedAt);
+    let without_scanned_at: Vec<Option<Column>> = columns
+        .iter()
+        .filter(|column| **column != Column::ScannedAt)
+        .copied()
+        .map(Some)
+        .collect();
+    match position {
+        Some(position) if existing == without_scanned_at => Ok(CsvAppend::MissingScannedAt(position)),
+        _ => {
+            let headers: Vec<String> = rdr.headers()?.iter().map(str::to_string).collect();
+            Err(
+                format!(
+                    "{} {}: [{}] != [{}]",
+                    path,
+                    t("的列与本次输出的列不同，不能追加", "has different columns from this output, refusing to append"),
+                    headers.join(","),
+                    csv_header(columns).join(",")
+                ).into()
+            )
+        }
+    }
+}
+
+// 已有CSV文件的列，文件不存在或者是空文件返回None，有不认识的标题就返回错误
+fn csv_columns(path: &str) -> Result<Option<Vec<Column>>, Box<dyn Error>> {
+    if fs::metadata(path).map_or(true, |metadata| metadata.len() == 0) {
+        return Ok(None);
+    }
+    let mut rdr = csv::Reader::from_path(path)?;
+    let columns = rdr
+        .headers()?
+        .iter()
+        .map(|header| {
+            Column::from_header(header).ok_or_else(|| {
+                format!("{} {}: {}", path, t("中有不认识的列", "has an unknown column"), header)
+            })
+        })
+        .collect::<Result<Vec<Column>, String>>()?;
+    Ok(Some(columns))
+}
+
+// 在已有CSV的position位置加上扫描时间列，原有的标题不变，原有的行这一列为空
+fn insert_scanned_at(path: &str, position: usize) -> Result<(), Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let mut header: Vec<String> = rdr.headers()?.iter().map(str::to_string).collect();
+    header.insert(position.min(header.len()), Column::ScannedAt.header().to_string());
+    let mut rows = vec![header];
+    for row in rdr.records() {
+        let mut row: Vec<String> = row?.iter().map(str::to_string).collect();
+        row.insert(position.min(row.len()), String::new());
+        rows.push(row);
+    }
+    write_to_csv(path, rows)
+}
+
+// 把本次的结果追加到CSV的末尾，不重写已有的行(只缺少扫描时间列时重写一次)
+fn append_to_csv(path: &str, columns: &[Column], records: &[Record]) -> Result<(), Box<dyn Error>> {
+    let state = check_csv_append(path, columns)?;
+    if let CsvAppend::MissingScannedAt(position) = state {
+        insert_scanned_at(path, position)?;
+    }
+    let has_rows = state != CsvAppend::Empty;
+    let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+    // 原文件最后一行没有换行时补上，避免和追加的第一行连在一起
+    if has_rows {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n")?;
+        }
+    }
+    let mut wtr = Writer::from_writer(file);
+    if !has_rows {
+        wtr.write_record(csv_header(columns))?;
+    }
+    for record in records {
+        wtr.write_record(csv_row(record, columns))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// CSV：边扫描边写入临时文件，结束时排序写入最终的文件；追加模式只在文件末尾写入本次的结果
+pub struct CsvSink {
+    path: String,
+    columns: Vec<Column>,
+    sort_by: Vec<SortKey>,
+    mode: WriteMode,
+    partial: Option<Writer<File>>,
+    records: Vec<Record>,
+}
+
+impl CsvSink {
+    pub fn new(path: &str, columns: Vec<Column>, sort_by: Vec<SortKey>, mode: WriteMode) -> Self {
+        CsvSink {
+            path: path.to_string(),
+            columns,
+            sort_by,
+            mode,
+            partial: None,
+            records: Vec::new(),
+        }
+    }
+}
+
+impl ResultSink for CsvSink {
+    fn open(&mut self) -> Result<(), Box<dyn Error>> {
+        // 继续中断的扫描时，按中断前写入的列继续写
+        if self.mode == WriteMode::Resume {
+            if let Some(columns) = csv_columns(&self.path)? {
+                self.columns = columns;
+            }
+        }
+        // 开始扫描前就检查能否追加，不要扫描完才发现
+        if matches!(self.mode, WriteMode::Append | WriteMode::Resume) {
+            check_csv_append(&self.path, &self.columns)?;
+        }
+        // 合并时保留文件中本次不输出的列，有不认识的列就拒绝合并，避免重写文件时丢掉
+        if let WriteMode::Merge(_) = self.mode {
+            for column in csv_columns(&self.path)?.unwrap_or_default() {
+                if !self.columns.contains(&column) {
+                    self.columns.push(column);
+                }
+            }
+        }
+        let mut wtr = Writer::from_writer(File::create(partial_path(&self.path))?);
+        wtr.write_record(csv_header(&self.columns))?;
+        wtr.flush()?;
+        self.partial = Some(wtr);
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), Box<dyn Error>> {
+        if let Some(wtr) = self.partial.as_mut() {
+            wtr.write_record(csv_row(record, &self.columns))?;
+            wtr.flush()?;
+        }
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut records = std::mem::take(&mut self.records);
+        if matches!(self.mode, WriteMode::Append | WriteMode::Resume) {
+            sort_records(&mut records, &self.sort_by);
+            append_to_csv(&self.path, &self.columns, &records)?;
+        } else {
+            let records = combine_with_existing(&self.path, self.mode, records, &self.sort_by)?;
+            let mut rows = vec![csv_header(&self.columns)];
+            rows.extend(records.iter().map(|record| csv_row(record, &self.columns)));
+            write_to_csv(&self.path, rows)?;
+        }
+        self.partial = None;
+        fs::remove_file(partial_path(&self.path))?;
+        Ok(())
+    }
+}
+
+// JSON/JSON Lines：边扫描边以JSON Lines写入临时文件，结束时排序写入最终的文件
+pub struct JsonSink {
+    path: String,
+    sort_by: Vec<SortKey>,
+    mode: WriteMode,
+    lines: bool, // true输出JSON Lines，false输出JSON数组
+    partial: Option<BufWriter<File>>,
+    records: Vec<Record>,
+}
+
+impl JsonSink {
+    pub fn new(path: &str, sort_by: Vec<SortKey>, mode: WriteMode, lines: bool) -> Self {
+        JsonSink {
+            path: path.to_string(),
+            sort_by,
+            mode,
+            lines,
+            partial: None,
+            records: Vec::new(),
+        }
+    }
+}
+
+impl ResultSink for JsonSink {
+    fn open(&mut self) -> Result<(), Box<dyn Error>> {
+        self.partial = Some(BufWriter::new(File::create(partial_path(&self.path))?));
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), Box<dyn Error>> {
+        if let Some(wtr) = self.partial.as_mut() {
+            serde_json::to_writer(&mut *wtr, record)?;
+            wtr.write_all(b"\n")?;
+            wtr.flush()?;
+        }
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        let records = std::mem::take(&mut self.records);
+        let records = combine_with_existing(&self.path, self.mode, records, &self.sort_by)?;
+        if self.lines {
+            write_to_jsonl(&self.path, &records)?;
+        } else {
+            write_to_json(&self.path, &records)?;
+        }
+        self.partial = None;
+        fs::remove_file(partial_path(&self.path))?;
+        Ok(())
+    }
+}
+
+// 根据输出格式创建输出端，JSON输出全部字段，不受columns影响
+pub fn create_sink(
+    format: OutputFormat,
+    path: &str,
+    mut columns: Vec<Column>,
+    sort_by: Vec<SortKey>,
+    mode: WriteMode
+) -> Box<dyn ResultSink> {
+    // 追加和合并时，文件中有多次扫描的结果，加上扫描时间列；继续中断的扫描时沿用文件中已有的列
+    if matches!(mode, WriteMode::Append | WriteMode::Merge(_)) && !columns.contains(&Column::ScannedAt) {
+        columns.push(Column::ScannedAt);
+    }
+    match format {
+        OutputFormat::Csv => Box::new(CsvSink::new(path, columns, sort_by, mode)),
+        OutputFormat::Json => Box::new(JsonSink::new(path, sort_by, mode, false)),
+        OutputFormat::Jsonl => Box::new(JsonSink::new(path, sort_by, mode, true)),
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use std::{ fs, io };
+
+// 响应头的匹配条件，名称不区分大小写，值包含指定的字符串
+#[derive(Debug, Deserialize, Clone)]
+pub struct HeaderPattern {
+    pub name: String,
+    pub contains: String,
+}
+
+// 指纹规则：请求路径、请求方法，以及期望的状态码、响应头、响应体
+#[derive(Debug, Deserialize, Clone)]
+pub struct Rule {
+    pub name: String,
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub status: Vec<u16>, // 为空就不检查状态码
+    #[serde(default)]
+    pub headers: Vec<HeaderPattern>,
+    #[serde(default)]
+    pub body: Vec<String>, // 响应体需要包含的字符串
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl Rule {
+    // 检查响应是否符合规则，headers的名称必须是小写
+    pub fn matches(&self, status: Option<u16>, headers: &[(String, String)], body: &str) -> bool {
+        if !self.status.is_empty() && !status.is_some_and(|code| self.status.contains(&code)) {
+            return false;
+        }
+        let headers_matched = self.headers.iter().all(|pattern| {
+            let name = pattern.name.to_ascii_lowercase();
+            headers.iter().any(|(n, v)| *n == name && v.contains(&pattern.contains))
+        });
+        headers_matched && self.body.iter().all(|pattern| body.contains(pattern.as_str()))
+    }
+}
+
+pub static JETBRAINS_RULE: &str = "jetbrains-license-server";
+
+// 内置的规则
+pub fn builtin_rules() -> Vec<Rule> {
+    vec![Rule {
+        name: JETBRAINS_RULE.to_string(),
+        path: "/".to_string(),
+        method: "HEAD".to_string(),
+        status: vec![],
+        headers: vec![HeaderPattern {
+            name: "location".to_string(),
+            contains: "https://account.jetbrains.com/fls-auth".to_string(),
+        }],
+        body: vec![],
+    }]
+}
+
+// 加载规则文件（JSON数组），文件不存在就只使用内置的规则
+pub fn load_rules(path: &str) -> io::Result<Vec<Rule>> {
+    let mut rules = builtin_rules();
+    match fs::read_to_string(path) {
+        Ok(text) => {
+            let custom: Vec<Rule> = serde_json::from_str(&text)?;
+            rules.extend(custom);
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e);
+        }
+    }
+    Ok(rules)
+}
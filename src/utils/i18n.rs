@@ -0,0 +1,201 @@
+use clap::Command;
+use std::sync::OnceLock;
+
+// 界面语言，影响命令行帮助、CSV标题和日志，JSON输出的字段名不受影响
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Zh,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+// 设置当前的语言，只在程序启动时设置一次
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+pub fn lang() -> Lang {
+    *LANG.get_or_init(system_lang)
+}
+
+// 根据系统的区域设置选择语言，获取不到就使用中文
+pub fn system_lang() -> Lang {
+    match sys_locale::get_locale() {
+        Some(locale) if locale.to_ascii_lowercase().starts_with("zh") => Lang::Zh,
+        Some(_) => Lang::En,
+        None => Lang::Zh,
+    }
+}
+
+// 在clap解析之前，从命令行参数中找出--名称的值(--name value或--name=value)
+pub fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = format!("--{}", name);
+    args.iter().enumerate().find_map(|(i, arg)| {
+        match arg.strip_prefix(&flag) {
+            Some("") => args.get(i + 1).cloned(),
+            Some(rest) => rest.strip_prefix('=').map(|v| v.to_string()),
+            None => None,
+        }
+    })
+}
+
+pub fn parse_lang(value: &str) -> Option<Lang> {
+    match value {
+        "en" => Some(Lang::En),
+        "zh" => Some(Lang::Zh),
+        _ => None,
+    }
+}
+
+// 命令行中的--lang，帮助信息需要在clap解析之前确定语言
+pub fn lang_from_args() -> Option<Lang> {
+    arg_value("lang").as_deref().and_then(parse_lang)
+}
+
+// 按当前的语言选择文本
+pub fn t(zh: &'static str, en: &'static str) -> &'static str {
+    match lang() {
+        Lang::Zh => zh,
+        Lang::En => en,
+    }
+}
+
+// 命令行帮助的英文文本，中文使用Args中的文档注释
+static EN_HELP: HelpTable = &[
+    ("file", "Input file (*.txt): domains, IPv4/IPv6 addresses and IPv4/IPv6 CIDRs"),
+    ("output", "Output file for the results"),
+    ("num", "Number of random IPs to generate from each IPv4/IPv6 CIDR"),
+    (
+        "format",
+        "Output format: csv, json (array) or jsonl (one record per line); JSON keys are always English and durations are in milliseconds",
+    ),
+    (
+        "columns",
+        "Comma-separated CSV columns in order, e.g. ip,colo,latency_ms,status; defaults to the columns of the scan mode; not allowed with JSON output",
+    ),
+    ("sort_by", "Comma-separated sort columns, prefix a name with \"-\" for descending, e.g. -verified,latency_ms"),
+    ("filter", "Filter condition, repeatable, e.g. colo=SJC, latency_ms<300, status!=403, cdn~cloud"),
+    (
+        "append",
+        "Append mode: keep existing results in the output file and append this run's results with a scan timestamp; a CSV that only lacks the Scanned At column gets it added, one with other differing columns is refused",
+    ),
+    (
+        "merge",
+        "Merge mode: deduplicate by address (and port); newest keeps this run's result, best keeps the one that sorts first (default: newest), off disables merging; columns of the existing CSV are kept, and a CSV with unknown columns is refused",
+    ),
+    ("summary_by", "Group the end-of-run summary by colo, country or region"),
+    ("summary", "Also write the summary to this file; JSON if it ends with .json, otherwise CSV"),
+    (
+        "report",
+        "Also generate a self-contained HTML report (e.g. report.html) with a sortable table, latency histogram, per-colo bar chart and colo map",
+    ),
+    (
+        "ui",
+        "Interface during the scan: plain (log lines only), progress (log lines plus a progress bar) or tui (full-screen live dashboard)",
+    ),
+    ("rate", "Maximum number of addresses started per second (token bucket); unlimited by default, only bounded by --pool"),
+    (
+        "per_prefix_rate",
+        "Maximum number of addresses started per second within one /24 (IPv4) or /48 (IPv6), to avoid hammering a single network",
+    ),
+    ("prober", "Backend that sends the probes: the curl command, or the built-in HTTP/1.1 client (no curl needed, http:// only)"),
+    ("connect_timeout", "Connect timeout in seconds, fractions allowed"),
+    ("max_time", "Total timeout of each request in seconds, fractions allowed"),
+    ("retries", "Retries (at most 10) for transient errors such as timeouts and connection resets; the backoff doubles each time, up to --max-time"),
+    (
+        "adaptive_timeout",
+        "Adaptive timeout: once enough requests succeeded, tighten the timeout to 3x the p90 latency, which greatly shortens scans of mostly-dead ranges",
+    ),
+    (
+        "tcp_prefilter",
+        "Two-stage scan: do a quick TCP connect to the target port first (as many at once as --pool) and only probe reachable addresses over HTTP",
+    ),
+    ("tcp_timeout", "Connect timeout of the TCP pre-filter in seconds, fractions allowed"),
+    ("checkpoint", "File to save a checkpoint (remaining targets, seed, completed addresses) to when the scan is interrupted with Ctrl-C"),
+    (
+        "resume",
+        "Continue an interrupted scan from a checkpoint file instead of the input file; results are appended to the output, use the same options as before",
+    ),
+    ("pool", "Number of curl probes running in parallel"),
+    ("fingerprint", "Fingerprint mode: probe with the built-in and rule file rules and output the matched rule names"),
+    ("rules", "Fingerprint rule file (*.json); only built-in rules are used if it does not exist"),
+    ("dns", "DNS server used to resolve domains (e.g. 1.1.1.1, 127.0.0.1:5353); defaults to the system resolver"),
+    ("host", "Preferred-domain mode: probe every IP with this domain as Host/SNI and verify the trace h= field"),
+    ("update_cf_ranges", "Download the latest Cloudflare IP ranges into cloudflare-ranges.txt"),
+    ("lang", "Language of help text, CSV headers and logs; defaults to the system locale"),
+    ("seed", "Seed for random IP generation; the same seed generates the same IPs, random if omitted"),
+    (
+        "config",
+        "Config file (TOML); defaults to config.toml in the current directory, then in the user config directory. Command-line flags take precedence; switches turned on in the config can be turned off with e.g. --tcp-prefilter=false",
+    ),
+    ("profile", "Use the values of [profile.NAME] from the config file on top of its top-level values"),
+    ("db", "SQLite database file; when set, every scan's arguments and results are stored in it"),
+];
+
+// (参数id, 英文帮助)
+type HelpTable = &'static [(&'static str, &'static str)];
+
+// 子命令的英文说明和子命令特有参数的帮助文本，scan、jetbrains和expand的参数与顶层相同，使用EN_HELP
+static EN_SUBCOMMANDS: &[(&str, &str, HelpTable)] = &[
+    (
+        "scan",
+        "Scan whether addresses are served through the Cloudflare CDN (default); --fingerprint scans with fingerprint rules",
+        &[],
+    ),
+    (
+        "jetbrains",
+        "Scan for JetBrains license servers, handshake with them and verify the response signatures against --ca-file",
+        &[
+            (
+                "ca_file",
+                "Root certificate(s) of JetBrains license servers (PEM) used to verify handshake signatures; if the file is missing nothing is verified",
+            ),
+        ],
+    ),
+    ("locations", "List all Cloudflare colos (locations.json)", &[("update", "Download locations.json again")]),
+    (
+        "expand",
+        "Only expand the input file with the current -n and seed, without scanning; per-line counts go to stderr",
+        &[("output", "Write the expanded addresses to this file instead of stdout")],
+    ),
+    ("lookup", "Look up a colo code (e.g. SJC) or city", &[("colo", "Colo code or city name")]),
+    (
+        "history",
+        "Show latency and colo of an address across past scans (uses --db, default scans.db)",
+        &[("ip", "IP address or domain")],
+    ),
+    (
+        "diff",
+        "Compare two result files (CSV/JSON/JSON Lines): appeared, disappeared, colo changes and latency changes",
+        &[
+            ("old", "Old result file"),
+            ("new", "New result file"),
+            ("threshold", "Only report latency changes larger than this (ms)"),
+        ],
+    ),
+];
+
+// 英文环境下替换命令行的帮助文本
+pub fn localize_command(cmd: Command) -> Command {
+    if lang() == Lang::Zh {
+        return cmd;
+    }
+    let mut cmd = localize_args(cmd.about("Bulk scan whether addresses are served through the Cloudflare CDN."), EN_HELP);
+    for (name, about, args) in EN_SUBCOMMANDS {
+        if cmd.find_subcommand(name).is_some() {
+            cmd = cmd.mut_subcommand(*name, |sub| localize_args(localize_args(sub, EN_HELP), args).about(*about));
+        }
+    }
+    cmd
+}
+
+fn localize_args(mut cmd: Command, table: HelpTable) -> Command {
+    for (id, help) in table.iter() {
+        if cmd.get_arguments().any(|arg| arg.get_id() == *id) {
+            cmd = cmd.mut_arg(*id, |arg| arg.help(*help));
+        }
+    }
+    cmd
+}
@@ -0,0 +1,280 @@
+use crate::utils::curl::{ build_url, http_request, run_fingerprint_rules, HttpResponse };
+use crate::utils::fingerprint::Rule;
+use crate::utils::i18n::t;
+use crate::utils::models::{ LicenseServer, Record, Target };
+use crate::utils::prober::Prober;
+use crate::utils::timeout::TimeoutPolicy;
+
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use log::{ debug, info, warn };
+use openssl::{
+    hash::MessageDigest,
+    sign::Verifier,
+    stack::Stack,
+    x509::{ store::{ X509Store, X509StoreBuilder }, X509StoreContext, X509 },
+};
+use rand::Rng;
+use std::{ error::Error, fs, io, time::Duration };
+
+// 模拟IntelliJ IDEA Ultimate客户端
+static PRODUCT_CODE: &str = "49c202d4-ac56-452b-bb84-735056242fb3";
+static BUILD_NUMBER: &str = "2024.1";
+static VERSION_NUMBER: &str = "2024100";
+
+// 获取XML标签的内容
+fn xml_tag<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start_tag = format!("<{}>", tag);
+    let end_tag = format!("</{}>", tag);
+    let start = xml.find(&start_tag)? + start_tag.len();
+    let end = start + xml[start..].find(&end_tag)?;
+    Some(&xml[start..end])
+}
+
+// 读取校验签名使用的根证书(PEM，可以包含多个证书)，文件不存在返回None
+pub fn load_trusted_certs(path: &str) -> Result<Option<X509Store>, Box<dyn Error>> {
+    let pem = match fs::read(path) {
+        Ok(pem) => pem,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(e.into());
+        }
+    };
+    let mut builder = X509StoreBuilder::new()?;
+    for cert in X509::stack_from_pem(&pem)? {
+        builder.add_cert(cert)?;
+    }
+    Ok(Some(builder.build()))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// 响应开头注释中的签名。许可证服务器的格式是"算法-十六进制签名-Base64证书"，证书后面可以跟着中间证书；
+// 只有十六进制签名的是旧的格式，没有证书，无法校验
+struct Signature {
+    digest: MessageDigest,
+    bytes: Vec<u8>,
+    certs: Vec<X509>, // 第一个是签名的证书
+}
+
+fn parse_signature(comment: &str) -> Result<Option<Signature>, String> {
+    let malformed = || t("响应中的签名格式错误", "malformed signature in the response").to_string();
+    let parts: Vec<&str> = comment.split('-').collect();
+    if let [hex] = parts[..] {
+        return decode_hex(hex).map(|_| None).ok_or_else(malformed);
+    }
+    let [algorithm, hex, certs @ ..] = &parts[..] else {
+        return Err(malformed());
+    };
+    let digest = match *algorithm {
+        "SHA1withRSA" => MessageDigest::sha1(),
+        "SHA256withRSA" => MessageDigest::sha256(),
+        "SHA512withRSA" => MessageDigest::sha512(),
+        other => {
+            return Err(format!("{}: {}", t("不支持的签名算法", "unsupported signature algorithm"), other));
+        }
+    };
+    let bytes = decode_hex(hex).ok_or_else(malformed)?;
+    let certs = certs
+        .iter()
+        .map(|cert| {
+            let der = STANDARD.decode(cert).map_err(|_| malformed())?;
+            X509::from_der(&der).map_err(|_| malformed())
+        })
+        .collect::<Result<Vec<X509>, String>>()?;
+    if certs.is_empty() {
+        return Err(malformed());
+    }
+    Ok(Some(Signature { digest, bytes, certs }))
+}
+
+// 校验签名：签名的证书要能通过中间证书链到信任的根证书，并且用它的公钥校验注释后面的XML
+fn verify_signature(signature: &Signature, content: &str, trusted: &X509Store) -> Result<(), Box<dyn Error>> {
+    let (leaf, intermediates) = signature.certs.split_first().ok_or("no certificate")?;
+    let mut chain = Stack::new()?;
+    for cert in intermediates {
+        chain.push(cert.clone())?;
+    }
+    let mut context = X509StoreContext::new()?;
+    let chained = context.init(trusted, leaf, &chain, |context| {
+        Ok(context.verify_cert()?.then_some(()).ok_or_else(|| context.error()))
+    })?;
+    chained.map_err(|e| format!("{}: {}", t("证书链校验失败", "certificate chain rejected"), e))?;
+    let key = leaf.public_key()?;
+    let mut verifier = Verifier::new(signature.digest, &key)?;
+    verifier.update(content.as_bytes())?;
+    match verifier.verify(&signature.bytes)? {
+        true => Ok(()),
+        false => Err(t("签名与响应的内容不一致", "signature does not match the response").into()),
+    }
+}
+
+// 检查签名过的响应：<!-- 签名 --> 后面跟着XML，responseCode为OK，并且salt与请求的一致，
+// 返回签名是否通过了校验；没有信任的根证书、旧格式的签名或者校验失败都返回false
+fn check_signed_response(response: &HttpResponse, salt: &str, trusted: Option<&X509Store>) -> Result<bool, String> {
+    let body = response.body.trim_start();
+    let (comment, content) = body
+        .strip_prefix("<!--")
+        .and_then(|rest| rest.split_once("-->"))
+        .ok_or(t("响应中没有签名", "no signature in the response"))?;
+    let signature = parse_signature(comment.trim())?;
+    // 签名的内容是注释后面换行之后的XML
+    let content = content.strip_prefix("\r\n").or_else(|| content.strip_prefix('\n')).unwrap_or(content);
+    match xml_tag(body, "responseCode") {
+        Some("OK") => {}
+        code => {
+            return Err(format!("responseCode: {}", code.unwrap_or_default()));
+        }
+    }
+    if xml_tag(body, "salt") != Some(salt) {
+        return Err(
+            t("响应中的salt与请求的不一致", "salt in the response does not match the request").to_string()
+        );
+    }
+    let (Some(signature), Some(trusted)) = (signature, trusted) else {
+        return Ok(false);
+    };
+    match verify_signature(&signature, content, trusted) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            debug!("{}: {}", t("签名校验失败", "signature verification failed"), e);
+            Ok(false)
+        }
+    }
+}
+
+// 执行obtainTicket、ping、releaseTicket握手，检查服务器是否按许可证服务器的协议响应并原样返回salt，
+// 有信任的根证书时校验两次响应的签名，返回服务器的信息和obtainTicket响应的状态码
+pub fn handshake(
+    addr: &str,
+    prober: &dyn Prober,
+    timeouts: &TimeoutPolicy,
+    trusted: Option<&X509Store>
+) -> Result<(LicenseServer, String), String> {
+    let mut rng = rand::thread_rng();
+    let salt = rng.gen::<u32>().to_string();
+    let machine_id = format!("{:032x}", rng.gen::<u128>());
+
+    let query = format!(
+        "/rpc/obtainTicket.action?buildNumber={}&clientVersion=16&hostName=localhost&machineId={}&productCode={}&productFamilyId={}&salt={}&secure=false&userName=user&version={}&versionNumber={}",
+        BUILD_NUMBER,
+        machine_id,
+        PRODUCT_CODE,
+        PRODUCT_CODE,
+        salt,
+        VERSION_NUMBER,
+        VERSION_NUMBER
+    );
+    let url = build_url(addr, &query).ok_or(t("无效的地址", "invalid address"))?;
+    let ticket = http_request(prober, timeouts, &url, "GET").map_err(|e| e.to_string())?;
+    let ticket_verified = check_signed_response(&ticket, &salt, trusted).map_err(|e| format!("obtainTicket: {}", e))?;
+
+    let url = build_url(addr, &format!("/rpc/ping.action?machineId={}&salt={}", machine_id, salt)).ok_or(
+        t("无效的地址", "invalid address")
+    )?;
+    let ping = http_request(prober, timeouts, &url, "GET").map_err(|e| e.to_string())?;
+    let ping_verified = check_signed_response(&ping, &salt, trusted).map_err(|e| format!("ping: {}", e))?;
+
+    // 释放租约，避免占用服务器的许可证数量
+    if let Some(ticket_id) = xml_tag(&ticket.body, "ticketId") {
+        let release = format!(
+            "/rpc/releaseTicket.action?machineId={}&salt={}&ticketId={}",
+            machine_id,
+            salt,
+            ticket_id
+        );
+        if let Some(url) = build_url(addr, &release) {
+            let _ = http_request(prober, timeouts, &url, "GET");
+        }
+    }
+
+    // ticketProperties的格式：licensee=xxx\tlicenseType=0\t
+    let properties = xml_tag(&ticket.body, "ticketProperties").unwrap_or_default();
+    let property = |name: &str| {
+        properties
+            .split(['\t', '\n'])
+            .find_map(|item| item.trim().strip_prefix(name)?.strip_prefix('='))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let version = xml_tag(&ping.body, "serverVersion")
+        .or_else(|| xml_tag(&ticket.body, "serverVersion"))
+        .or_else(|| {
+            ticket.headers
+                .iter()
+                .find(|(name, _)| name == "server")
+                .map(|(_, value)| value.as_str())
+        })
+        .unwrap_or_default()
+        .to_string();
+    let lease_period = xml_tag(&ticket.body, "prolongationPeriod")
+        .and_then(|period| period.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or_default();
+
+    let server = LicenseServer {
+        salt_echoed: true,
+        verified: ticket_verified && ping_verified,
+        version,
+        licensee: property("licensee"),
+        license_type: property("licenseType"),
+        lease_period,
+        response_time: ticket.elapsed + ping.elapsed,
+    };
+    Ok((server, ticket.status_code.unwrap_or_default()))
+}
+
+// JetBrains模式：先匹配重定向到fls-auth的规则，再执行握手，区分按协议响应的许可证服务器和只做了重定向的地址
+pub fn run_jetbrains_check(
+    target: &Target,
+    rules: &[Rule],
+    prober: &dyn Prober,
+    timeouts: &TimeoutPolicy,
+    trusted: Option<&X509Store>
+) -> Result<Record, io::Error> {
+    let ip = target.addr.as_str();
+    let fingerprint = run_fingerprint_rules(target, rules, prober, timeouts);
+    // 规则的探测没有收到任何HTTP响应，不用再握手
+    if matches!(&fingerprint, Err(e) if e.kind() == io::ErrorKind::NotConnected) {
+        return fingerprint;
+    }
+    match handshake(ip, prober, timeouts, trusted) {
+        Ok((server, http_status_code)) => {
+            info!(
+                "{} | JetBrains License server | {} | {} | {} ms | {}",
+                ip,
+                server.version,
+                server.licensee,
+                server.response_time.as_millis(),
+                match server.verified {
+                    true => t("签名已验证", "signature verified"),
+                    false => t("签名未验证", "signature not verified"),
+                }
+            );
+            let mut record = fingerprint.unwrap_or_else(|_| Record {
+                ip: ip.to_string(),
+                delay: server.response_time,
+                http_status_code,
+                ..Record::default()
+            });
+            record.license_server = Some(server);
+            Ok(record)
+        }
+        Err(e) => {
+            warn!("{} | {}: {}", ip, t("许可证服务器握手失败", "license server handshake failed"), e);
+            // 只有重定向，握手失败的，也保留下来
+            let mut record = fingerprint?;
+            record.license_server = Some(LicenseServer::default());
+            Ok(record)
+        }
+    }
+}
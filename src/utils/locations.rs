@@ -0,0 +1,81 @@
+use crate::utils::i18n::t;
+use crate::utils::models::Airport;
+
+use reqwest::Error;
+use std::fs;
+
+static LOCATIONS: &str = "locations.json";
+static LOCATIONS_URL: &str = "https://speed.cloudflare.com/locations";
+
+/// 用于下载locations.json文件
+async fn download_file(url: &str, path: &str) -> Result<(), Error> {
+    let response = reqwest::get(url).await?;
+    let content = response.text().await?;
+    fs::write(path, content).expect("Unable to write file");
+    Ok(())
+}
+
+// 加载locations.json文件，文件不存在、读取失败或者要求更新时就重新下载
+pub async fn load_airports(update: bool) -> Result<Vec<Airport>, Box<dyn std::error::Error>> {
+    let locations = match fs::read_to_string(LOCATIONS) {
+        Ok(data) if !update => data,
+        _ => {
+            download_file(LOCATIONS_URL, LOCATIONS).await?;
+            fs::read_to_string(LOCATIONS).expect("Unable to read file")
+        }
+    };
+    // 解析为 Airport 结构体
+    Ok(serde_json::from_str(&locations)?)
+}
+
+fn print_header() {
+    println!(
+        "{:<6} {:<8} {:<16} {:<24} {:>9} {:>10}",
+        "colo",
+        "alpha-2",
+        t("地区", "region"),
+        t("城市", "city"),
+        "lat",
+        "lon"
+    );
+}
+
+fn print_airport(airport: &Airport) {
+    println!(
+        "{:<6} {:<8} {:<16} {:<24} {:>9.4} {:>10.4}",
+        airport.iata,
+        airport.cca2,
+        airport.region,
+        airport.city,
+        airport.lat,
+        airport.lon
+    );
+}
+
+// 列出全部的数据中心，按地区和代码排序
+pub fn print_locations(airports: &[Airport]) {
+    let mut airports: Vec<&Airport> = airports.iter().collect();
+    airports.sort_by(|a, b| a.region.cmp(&b.region).then_with(|| a.iata.cmp(&b.iata)));
+    print_header();
+    for airport in &airports {
+        print_airport(airport);
+    }
+    println!("\n{}: {}", t("数据中心的数量", "Colos"), airports.len());
+}
+
+// 查询数据中心：代码完全匹配，或者城市名称包含查询的文本，不区分大小写
+pub fn lookup(airports: &[Airport], query: &str) {
+    let query = query.trim().to_lowercase();
+    let found: Vec<&Airport> = airports
+        .iter()
+        .filter(|a| a.iata.to_lowercase() == query || a.city.to_lowercase().contains(&query))
+        .collect();
+    if found.is_empty() {
+        println!("{}: {}", t("没有找到这个数据中心", "No colo matches"), query);
+        return;
+    }
+    print_header();
+    for airport in found {
+        print_airport(airport);
+    }
+}
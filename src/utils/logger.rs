@@ -13,7 +13,8 @@ pub fn init_logger() -> Result<(), fern::InitError> {
             )
         })
         .level(log::LevelFilter::Info)
-        .chain(std::io::stdout())
+        // 经过进度条输出，避免日志把进度条打乱
+        .chain(fern::Output::call(|record| crate::utils::progress::log_line(&record.args().to_string())))
         .apply()?;
     Ok(())
 }
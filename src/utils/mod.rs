@@ -1,5 +1,24 @@
+pub mod cdn;
+pub mod checkpoint;
+pub mod columns;
+pub mod config;
 pub mod curl;
+pub mod db;
+pub mod diff;
+pub mod dns;
 pub mod files;
+pub mod fingerprint;
+pub mod i18n;
+pub mod jetbrains;
+pub mod locations;
 pub mod logger;
 pub mod network;
+pub mod prefilter;
+pub mod prober;
+pub mod progress;
+pub mod rate;
+pub mod scanner;
+pub mod timeout;
+pub mod report;
+pub mod summary;
 pub mod models;
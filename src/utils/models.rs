@@ -1,22 +1,111 @@
-use serde::{ Deserialize, Serialize };
+use serde::{ Deserialize, Deserializer, Serialize, Serializer };
 use std::time;
 
+// 时长输出为毫秒数，方便下游程序处理
+fn serialize_millis<S>(duration: &time::Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer
+{
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+fn deserialize_millis<'de, D>(deserializer: D) -> Result<time::Duration, D::Error>
+    where D: Deserializer<'de>
+{
+    Ok(time::Duration::from_millis(u64::deserialize(deserializer)?))
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Airport {
     pub iata: String,
+    pub lat: f64,
+    pub lon: f64,
     pub cca2: String,
     pub region: String,
     pub city: String,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct Record {
     pub ip: String,
+    pub domain: String, // 作为Host/SNI的域名，直接扫描IP时为空
+    pub resolved_ip: String, // 域名解析后实际连接的IP，直接扫描IP时为空
     pub colo: String,
     pub country: String,
     pub region: String,
     pub city: String,
+    #[serde(
+        rename = "delay_ms",
+        serialize_with = "serialize_millis",
+        deserialize_with = "deserialize_millis"
+    )]
     pub delay: time::Duration,
-    pub is_jetbrains: bool, // 是否为JetBrains的License服务器
+    pub fingerprints: Vec<String>, // 匹配到的指纹规则名称
     pub http_status_code: String,
+    pub cdn: String, // 识别出来的CDN，如cloudflare、cloudfront、fastly
+    pub detection: String, // 识别为CDN的依据，多个用+连接，如cf-ray+server+ip-range
+    pub license_server: Option<LicenseServer>, // JetBrains许可证服务器的握手结果
+    pub scanned_at: String, // 得到这条结果的本地时间，追加和合并结果文件时用来区分每次扫描
+}
+
+impl Record {
+    // 同一个地址在多次扫描中的唯一标识，地址带端口时端口也是标识的一部分，域名的每个解析IP分别计算
+    pub fn key(&self) -> (String, String) {
+        (self.ip.clone(), self.resolved_ip.clone())
+    }
+}
+
+// JetBrains许可证服务器obtainTicket/ping握手的结果
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LicenseServer {
+    pub salt_echoed: bool, // 握手成功，响应带有签名，并且原样返回了请求的salt
+    pub verified: bool, // 响应的签名通过了校验，签名证书由信任的JetBrains许可证服务器根证书签发
+    pub version: String, // 服务器版本
+    pub licensee: String, // 被授权者
+    pub license_type: String,
+    #[serde(
+        rename = "lease_period_ms",
+        serialize_with = "serialize_millis",
+        deserialize_with = "deserialize_millis"
+    )]
+    pub lease_period: time::Duration, // 租期(prolongationPeriod)
+    #[serde(
+        rename = "response_time_ms",
+        serialize_with = "serialize_millis",
+        deserialize_with = "deserialize_millis"
+    )]
+    pub response_time: time::Duration, // 握手的耗时
+}
+
+// 扫描目标，域名经过DNS解析后，每个A/AAAA记录都是一个独立的目标
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Target {
+    pub addr: String, // 原始地址（IP、CIDR生成的IP、域名、URL）
+    pub host: Option<String>, // 请求时使用的Host/SNI
+    pub resolved_ip: Option<String>, // 固定连接到这个IP（curl的--resolve参数）
+    pub verify_host: bool, // 是否校验trace中的h=字段与host一致
+}
+
+impl From<String> for Target {
+    fn from(addr: String) -> Self {
+        Target { addr, host: None, resolved_ip: None, verify_host: false }
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.resolved_ip {
+            Some(ip) => write!(f, "{} -> {}", self.addr, ip),
+            None => write!(f, "{}", self.addr),
+        }
+    }
+}
+
+// 扫描模式，决定CSV输出的列
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanMode {
+    Cdn,
+    Fingerprint,
+    Jetbrains,
 }
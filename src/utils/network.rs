@@ -1,47 +1,82 @@
 use ipnetwork::IpNetwork;
-use rand::{ prelude::SliceRandom, Rng };
-use std::{ collections::HashSet, net::{ IpAddr, Ipv4Addr, Ipv6Addr }, sync::{ mpsc, Arc, Mutex } };
+use rand::{ prelude::SliceRandom, rngs::StdRng, Rng, SeedableRng };
+use std::{ collections::HashSet, net::{ IpAddr, Ipv4Addr, Ipv6Addr }, sync::mpsc };
 use threadpool::ThreadPool;
 
+// 每一行使用独立的随机数生成器，种子由全局种子和这一行的内容决定（FNV-1a），与线程的执行顺序无关
+fn line_rng(seed: u64, line: &str) -> StdRng {
+    let hash = line.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ (byte as u64)).wrapping_mul(0x100000001b3)
+    });
+    StdRng::seed_from_u64(seed ^ hash)
+}
+
 // 处理IPv4、IPv6、CIDR、域名，是CIDR的话，就随机生成IP，否则就返回原字符串
+// 相同的种子和输入，生成的IP和顺序都相同
 pub fn process_ip_cidr_hosts(
     ip_addresses: Vec<String>,
     pool_size: usize,
-    count: usize
+    count: usize,
+    seed: u64
 ) -> Vec<String> {
+    shuffle_expanded(&expand_lines(ip_addresses, pool_size, count, seed), seed)
+}
+
+// 合并逐行展开的地址，按种子打乱顺序，就是扫描时使用的顺序
+pub fn shuffle_expanded(lines: &[(String, Vec<String>)], seed: u64) -> Vec<String> {
+    let mut ips: Vec<String> = lines
+        .iter()
+        .flat_map(|(_, ips)| ips.iter().cloned())
+        .collect();
+    // 先排序消除线程执行顺序的影响，再按种子打乱顺序
+    ips.sort();
+    ips.shuffle(&mut StdRng::seed_from_u64(seed));
+
+    ips
+}
+
+// 逐行展开，返回每一行和它生成的地址，顺序与输入的行相同
+pub fn expand_lines(
+    ip_addresses: Vec<String>,
+    pool_size: usize,
+    count: usize,
+    seed: u64
+) -> Vec<(String, Vec<String>)> {
     let pool_generate = ThreadPool::new(pool_size);
     let (tx_generate, rx_generate) = mpsc::channel();
-    let ip_addresses = Arc::new(Mutex::new(ip_addresses));
-    for item in ip_addresses.lock().unwrap().iter() {
+    for (index, item) in ip_addresses.iter().enumerate() {
         let tx_generate = tx_generate.clone();
         let cloned_item = item.clone();
         pool_generate.execute(move || {
+            let mut rng = line_rng(seed, &cloned_item);
             if count > 1 {
-                let ips = generate_ip_and_check_ip_type2(&cloned_item, count);
-                tx_generate.send(ips).unwrap();
+                let ips = generate_ip_and_check_ip_type2(&cloned_item, count, &mut rng);
+                tx_generate.send((index, ips)).unwrap();
             } else {
-                let ips = generate_ip_and_check_ip_type(&cloned_item);
-                tx_generate.send(ips).unwrap();
+                let ips = generate_ip_and_check_ip_type(&cloned_item, &mut rng);
+                tx_generate.send((index, ips)).unwrap();
             }
         });
     }
     drop(tx_generate);
 
-    // 从接受端迭代结果放到ips中
-    let mut ips: Vec<String> = Vec::new();
-    rx_generate.iter().for_each(|ips_batch| ips.extend(ips_batch));
-    // 打乱顺序
-    ips.shuffle(&mut rand::thread_rng());
+    // 从接受端迭代结果，按行号放回原来的位置
+    let mut lines: Vec<(String, Vec<String>)> = ip_addresses
+        .into_iter()
+        .map(|line| (line, Vec::new()))
+        .collect();
+    rx_generate.iter().for_each(|(index, ips_batch)| {
+        lines[index].1 = ips_batch;
+    });
 
-    ips
+    lines
 }
 
 // ---------------------------------分支1----------------------------------------------------
 
-fn generate_ip_and_check_ip_type(ip_address: &str) -> Vec<String> {
+fn generate_ip_and_check_ip_type(ip_address: &str, rng: &mut StdRng) -> Vec<String> {
     // 是CIDR的，处理方案，支持ipv4和ipv6的cidr，只生成单个IP
     if let Ok(ip_network) = ip_address.parse::<IpNetwork>() {
-        let mut rng = rand::thread_rng();
         match ip_network {
             IpNetwork::V4(v4_network) => {
                 let network = u32::from(v4_network.network());
@@ -73,14 +108,14 @@ fn generate_ip_and_check_ip_type(ip_address: &str) -> Vec<String> {
 
 // ---------------------------------分支2----------------------------------------------------
 
-fn generate_ip_and_check_ip_type2(ip_address: &str, count: usize) -> Vec<String> {
+fn generate_ip_and_check_ip_type2(ip_address: &str, count: usize, rng: &mut StdRng) -> Vec<String> {
     // 尝试解析为 CIDR 或 IP 地址
     if let Ok(ip_network) = ip_address.parse::<IpNetwork>() {
         return match ip_network {
             // 处理 IPv6 CIDR
-            _ if ip_network.is_ipv6() => generate_random_ipv6_in_cidr(ip_network, count),
+            _ if ip_network.is_ipv6() => generate_random_ipv6_in_cidr(ip_network, count, rng),
             // 处理 IPv4 CIDR
-            _ if ip_network.is_ipv4() => generate_random_ipv4_in_cidr(ip_network, count),
+            _ if ip_network.is_ipv4() => generate_random_ipv4_in_cidr(ip_network, count, rng),
             // 正常情况下，不应该到达这个分支，只处理网络段
             _ => unreachable!(),
         };
@@ -93,8 +128,7 @@ fn generate_ip_and_check_ip_type2(ip_address: &str, count: usize) -> Vec<String>
     vec![ip_address.to_string()]
 }
 
-fn generate_random_ipv6_in_cidr(ip_network: IpNetwork, count: usize) -> Vec<String> {
-    let mut rng = rand::thread_rng();
+fn generate_random_ipv6_in_cidr(ip_network: IpNetwork, count: usize, rng: &mut StdRng) -> Vec<String> {
     if ip_network.prefix() < 119 {
         if let IpNetwork::V6(cidr) = ip_network {
             let lower = u128::from(cidr.network());
@@ -123,9 +157,8 @@ fn generate_random_ipv6_in_cidr(ip_network: IpNetwork, count: usize) -> Vec<Stri
     }
 }
 
-fn generate_random_ipv4_in_cidr(ip_network: IpNetwork, count: usize) -> Vec<String> {
+fn generate_random_ipv4_in_cidr(ip_network: IpNetwork, count: usize, rng: &mut StdRng) -> Vec<String> {
     if let IpNetwork::V4(v4_network) = ip_network {
-        let mut rng = rand::thread_rng();
         let mut unique_ips = HashSet::new();
         let ip_range = v4_network.size();
 
@@ -0,0 +1,72 @@
+use crate::utils::checkpoint::StopHandle;
+use crate::utils::dns::split_port;
+use crate::utils::models::{ ScanMode, Target };
+
+use ipnetwork::IpNetwork;
+use std::{ sync::Arc, time::Duration };
+use tokio::{ net::TcpStream, sync::Semaphore, task::JoinSet, time::timeout };
+use url::Url;
+
+// 目标实际连接的主机和端口：固定了IP的连接这个IP，指纹识别模式下URL使用它的端口，
+// 带端口的地址(1.2.3.4:8080、[v6]:8080、域名:8080)使用这个端口，其他的请求80端口
+fn connect_addr(target: &Target, mode: ScanMode) -> Option<(String, u16)> {
+    let addr = target.addr.as_str();
+    let (host, port) = if addr.starts_with("http://") || addr.starts_with("https://") {
+        let url = Url::parse(addr).ok()?;
+        let host = url.host_str()?.trim_start_matches('[').trim_end_matches(']').to_string();
+        let port = match mode {
+            ScanMode::Cdn => 80,
+            _ => url.port_or_known_default()?,
+        };
+        (host, port)
+    } else if let Ok(network) = addr.parse::<IpNetwork>() {
+        (network.ip().to_string(), 80)
+    } else {
+        let (host, port) = split_port(addr);
+        (host, port.unwrap_or(80))
+    };
+    Some((target.resolved_ip.clone().unwrap_or(host), port))
+}
+
+async fn can_connect(host: String, port: u16, connect_timeout: Duration) -> bool {
+    matches!(timeout(connect_timeout, TcpStream::connect((host.as_str(), port))).await, Ok(Ok(_)))
+}
+
+// 第一阶段：对目标端口做一次TCP连接，只保留能连接上的目标，顺序保持不变，
+// 同时进行的连接数量是concurrency(--pool)
+pub async fn tcp_prefilter(
+    targets: Vec<Target>,
+    mode: ScanMode,
+    connect_timeout: Duration,
+    concurrency: usize,
+    stop: &StopHandle
+) -> Vec<Target> {
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    for (index, target) in targets.iter().enumerate() {
+        let Some((host, port)) = connect_addr(target, mode) else {
+            continue;
+        };
+        let semaphore = Arc::clone(&semaphore);
+        let stop = stop.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            // 中断后不再连接，保留下来交给检查点
+            if stop.stopped() {
+                return (index, true);
+            }
+            (index, can_connect(host, port, connect_timeout).await)
+        });
+    }
+    let mut reachable = vec![false; targets.len()];
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((index, ok)) = joined {
+            reachable[index] = ok;
+        }
+    }
+    targets
+        .into_iter()
+        .zip(reachable)
+        .filter_map(|(target, ok)| ok.then_some(target))
+        .collect()
+}
@@ -0,0 +1,319 @@
+use crate::utils::cdn::parse_headers;
+use crate::utils::curl::HttpResponse;
+use crate::utils::timeout::TimeoutPolicy;
+
+use std::{
+    io::{ self, BufRead, BufReader, Read, Write },
+    net::{ IpAddr, SocketAddr, TcpStream, ToSocketAddrs },
+    process::{ Command, Output, Stdio },
+    time::{ Duration, Instant },
+};
+use url::Url;
+
+// 一次HTTP请求
+pub struct ProbeRequest<'a> {
+    pub url: &'a str,
+    pub method: &'a str, // GET、HEAD等，HEAD只获取响应头
+    pub resolve: Option<&'a str>, // 固定连接到这个IP，Host/SNI仍然使用URL中的主机名(curl的--resolve)
+    pub timeouts: &'a TimeoutPolicy, // 这次扫描的超时和重试策略
+}
+
+// 发送探测请求的后端：连接失败、超时等网络错误返回没有状态码的响应，只有后端本身不能工作(如curl无法启动)时返回错误
+pub trait Prober: Send + Sync {
+    fn request(&self, request: &ProbeRequest) -> io::Result<HttpResponse>;
+}
+
+// 探测请求使用的后端
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProberKind {
+    Curl, // 调用curl命令
+    Native, // 程序内置的HTTP/1.1客户端，不需要安装curl，只支持http://
+}
+
+pub fn create_prober(kind: ProberKind) -> Box<dyn Prober> {
+    match kind {
+        ProberKind::Curl => Box::new(CurlProber::new()),
+        ProberKind::Native => Box::new(NativeProber::new()),
+    }
+}
+
+fn no_response(elapsed: Duration) -> HttpResponse {
+    HttpResponse { status_code: None, headers: vec![], body: String::new(), elapsed }
+}
+
+// 把响应分成响应头和响应体，解析出状态码
+fn parse_response(text: &str, elapsed: Duration) -> HttpResponse {
+    let (head, body) = text.split_once("\r\n\r\n").unwrap_or((text, ""));
+    let status_code = head
+        .lines()
+        .find(|line| line.starts_with("HTTP/"))
+        .and_then(|line| line.split(' ').nth(1))
+        .map(|code| code.to_string());
+    HttpResponse {
+        status_code,
+        headers: parse_headers(head),
+        body: body.to_string(),
+        elapsed,
+    }
+}
+
+// IPv6地址加上方括号
+fn bracket(ip: &str) -> String {
+    if ip.contains(':') { format!("[{}]", ip) } else { ip.to_string() }
+}
+
+// ---------------------------------curl----------------------------------------------------
+
+// 值得重试的curl退出码：28超时、52没有任何响应、55发送失败、56接收失败(如连接被重置)
+const TRANSIENT_EXIT_CODES: &[i32] = &[28, 52, 55, 56];
+
+#[derive(Default)]
+pub struct CurlProber {
+    connect_to: Option<SocketAddr>, // 全部请求都连接到这个地址(curl的--connect-to)
+}
+
+impl CurlProber {
+    pub fn new() -> Self {
+        CurlProber::default()
+    }
+
+    // 全部请求都连接到这个地址，测试时连接到本地的服务器
+    pub fn connect_to(mut self, addr: SocketAddr) -> Self {
+        self.connect_to = Some(addr);
+        self
+    }
+}
+
+// 终端的Ctrl-C会发给整个进程组，curl放到单独的进程组中，中断时进行中的请求能正常完成，结果和检查点都不会丢失
+#[cfg(unix)]
+fn detach_from_ctrl_c(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+#[cfg(windows)]
+fn detach_from_ctrl_c(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn detach_from_ctrl_c(_command: &mut Command) {}
+
+// 按超时策略执行curl命令，临时错误按退避时间重试，返回输出和最后一次请求的耗时
+fn run_curl(args: &[String], policy: &TimeoutPolicy) -> io::Result<(Output, Duration)> {
+    let mut attempt = 0;
+    loop {
+        let (connect, total) = policy.current();
+        let connect = format!("{:.3}", connect.as_secs_f64());
+        let total = format!("{:.3}", total.as_secs_f64());
+        let mut command = Command::new("curl");
+        command.args(args).args(["--connect-timeout", &connect, "--max-time", &total]);
+        detach_from_ctrl_c(&mut command);
+        let start_time = Instant::now(); // 开始时间
+        let output = command.stdout(Stdio::piped()).spawn()?.wait_with_output()?;
+        let elapsed = start_time.elapsed(); // 结束时间
+        match output.status.code() {
+            Some(0) => {
+                policy.record(elapsed);
+                return Ok((output, elapsed));
+            }
+            Some(code) if TRANSIENT_EXIT_CODES.contains(&code) && policy.retry(attempt) => {
+                attempt += 1;
+            }
+            _ => {
+                return Ok((output, elapsed));
+            }
+        }
+    }
+}
+
+impl Prober for CurlProber {
+    fn request(&self, request: &ProbeRequest) -> io::Result<HttpResponse> {
+        let method = request.method.to_ascii_uppercase();
+        let mut args: Vec<String> = vec![request.url.to_string(), "-s".to_string()];
+        if method == "HEAD" {
+            args.push("-I".to_string());
+        } else {
+            args.extend(["-i".to_string(), "-X".to_string(), method]);
+        }
+        if let Some(ip) = request.resolve {
+            let url = Url::parse(request.url).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let host = url.host_str().unwrap_or_default();
+            let port = url.port_or_known_default().unwrap_or(80);
+            args.extend(["--resolve".to_string(), format!("{}:{}:{}", host, port, bracket(ip))]);
+        }
+        if let Some(addr) = self.connect_to {
+            args.extend(["--connect-to".to_string(), format!("::{}:{}", bracket(&addr.ip().to_string()), addr.port())]);
+        }
+        let (output, elapsed) = run_curl(&args, request.timeouts)?;
+        Ok(parse_response(&String::from_utf8_lossy(&output.stdout), elapsed))
+    }
+}
+
+// ---------------------------------内置的HTTP/1.1客户端----------------------------------------
+
+#[derive(Default)]
+pub struct NativeProber {
+    connect_to: Option<SocketAddr>, // 全部请求都连接到这个地址
+}
+
+impl NativeProber {
+    pub fn new() -> Self {
+        NativeProber::default()
+    }
+
+    // 全部请求都连接到这个地址，测试时连接到本地的服务器
+    pub fn connect_to(mut self, addr: SocketAddr) -> Self {
+        self.connect_to = Some(addr);
+        self
+    }
+
+    // 实际连接的地址：connect_to、resolve指定的IP、URL中的主机
+    fn socket_addrs(&self, url: &Url, resolve: Option<&str>) -> io::Result<Vec<SocketAddr>> {
+        if let Some(addr) = self.connect_to {
+            return Ok(vec![addr]);
+        }
+        let port = url.port_or_known_default().unwrap_or(80);
+        if let Some(ip) = resolve {
+            let ip: IpAddr = ip.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        let host = url.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+        Ok((host, port).to_socket_addrs()?.collect())
+    }
+
+    // 发送一次请求，读取完整的响应
+    fn send(&self, url: &Url, method: &str, request: &ProbeRequest, deadline: Instant) -> io::Result<String> {
+        let resolve = request.resolve;
+        let (connect_timeout, _) = request.timeouts.current();
+        let mut stream: Option<TcpStream> = None;
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no address");
+        for addr in self.socket_addrs(url, resolve)? {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match TcpStream::connect_timeout(&addr, connect_timeout.min(remaining).max(Duration::from_millis(1))) {
+                Ok(connected) => {
+                    stream = Some(connected);
+                    break;
+                }
+                Err(e) => {
+                    last_error = e;
+                }
+            }
+        }
+        let mut stream = stream.ok_or(last_error)?;
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now()).max(Duration::from_millis(1));
+        stream.set_write_timeout(Some(remaining))?;
+        stream.write_all(
+            format!(
+                "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: curl_cdn-cgi_trace_rust\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+                method,
+                path,
+                host
+            ).as_bytes()
+        )?;
+
+        let mut reader = BufReader::new(stream);
+        let mut head = String::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+            }
+            reader.get_ref().set_read_timeout(Some(remaining))?;
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+            }
+            head.push_str(&line);
+            if line == "\r\n" || line == "\n" {
+                break;
+            }
+        }
+        let headers = parse_headers(&head);
+        let header = |name: &str| crate::utils::cdn::header(&headers, name).map(str::to_ascii_lowercase);
+        let mut body: Vec<u8> = Vec::new();
+        if method != "HEAD" {
+            let remaining = deadline.saturating_duration_since(Instant::now()).max(Duration::from_millis(1));
+            reader.get_ref().set_read_timeout(Some(remaining))?;
+            if header("transfer-encoding").is_some_and(|value| value.contains("chunked")) {
+                read_chunked(&mut reader, &mut body)?;
+            } else if let Some(length) = header("content-length").and_then(|value| value.parse::<u64>().ok()) {
+                reader.by_ref().take(length).read_to_end(&mut body)?;
+            } else {
+                reader.read_to_end(&mut body)?;
+            }
+        }
+        Ok(format!("{}{}", head, String::from_utf8_lossy(&body)))
+    }
+}
+
+// 读取chunked编码的响应体
+fn read_chunked<R: BufRead>(reader: &mut R, body: &mut Vec<u8>) -> io::Result<()> {
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line)?;
+        let size_hex = size_line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size_hex, 16).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if size == 0 {
+            return Ok(());
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut crlf = String::new();
+        reader.read_line(&mut crlf)?;
+    }
+}
+
+// 与curl的临时错误对应：超时、连接被重置、没有响应
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut |
+            io::ErrorKind::WouldBlock |
+            io::ErrorKind::ConnectionReset |
+            io::ErrorKind::ConnectionAborted |
+            io::ErrorKind::UnexpectedEof
+    )
+}
+
+impl Prober for NativeProber {
+    fn request(&self, request: &ProbeRequest) -> io::Result<HttpResponse> {
+        let url = Url::parse(request.url).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if url.scheme() != "http" {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{}: {}", url.scheme(), request.url)));
+        }
+        let method = request.method.to_ascii_uppercase();
+        let policy = request.timeouts;
+        let mut attempt = 0;
+        loop {
+            let (_, total) = policy.current();
+            let start_time = Instant::now();
+            let result = self.send(&url, &method, request, start_time + total);
+            let elapsed = start_time.elapsed();
+            match result {
+                Ok(text) => {
+                    policy.record(elapsed);
+                    return Ok(parse_response(&text, elapsed));
+                }
+                Err(e) if is_transient(&e) && policy.retry(attempt) => {
+                    attempt += 1;
+                }
+                Err(_) => {
+                    return Ok(no_response(elapsed));
+                }
+            }
+        }
+    }
+}
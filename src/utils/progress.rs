@@ -0,0 +1,231 @@
+use crate::utils::i18n::t;
+use crate::utils::models::Record;
+
+use crossterm::{ cursor, execute, queue, style::Print, terminal };
+use indicatif::{ ProgressBar, ProgressStyle };
+use std::{
+    collections::BTreeMap,
+    io::{ self, Write },
+    sync::{ atomic::{ AtomicBool, Ordering }, Mutex },
+    time::{ Duration, Instant },
+};
+
+// 扫描过程中的界面
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ui {
+    Plain, // 只输出每个地址的日志
+    Progress, // 日志加上底部的进度条
+    Tui, // 全屏的实时面板，不输出每个地址的日志
+}
+
+// 正在显示的进度条，日志要从进度条的上方输出，避免把进度条打乱
+static BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+// 全屏面板显示期间不输出日志
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// 日志的输出，由logger调用
+pub fn log_line(line: &str) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
+    match BAR.lock().unwrap().as_ref() {
+        Some(bar) if !bar.is_hidden() => bar.println(line),
+        _ => println!("{}", line),
+    }
+}
+
+const TOP: usize = 20;
+
+// 扫描的进度：已完成/总数、速率、剩余时间、成功和失败的数量
+pub struct Progress {
+    ui: Ui,
+    total: usize,
+    done: usize,
+    success: usize,
+    started: Instant,
+    last_draw: Option<Instant>,
+    top: Vec<Record>, // 目前最快的地址
+    colos: BTreeMap<String, usize>,
+    errors: BTreeMap<String, usize>,
+    active: bool,
+}
+
+impl Progress {
+    pub fn new(ui: Ui, total: usize) -> io::Result<Self> {
+        let progress = Progress {
+            ui,
+            total,
+            done: 0,
+            success: 0,
+            started: Instant::now(),
+            last_draw: None,
+            top: Vec::new(),
+            colos: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            active: true,
+        };
+        match ui {
+            Ui::Plain => {}
+            Ui::Progress => {
+                let bar = ProgressBar::new(total as u64);
+                bar.set_style(
+                    ProgressStyle::with_template(
+                        "[{elapsed_precise}] {wide_bar:.cyan/blue} {pos}/{len} {per_sec} ETA {eta} {msg}"
+                    ).unwrap_or_else(|_| ProgressStyle::default_bar())
+                );
+                bar.enable_steady_tick(Duration::from_millis(200));
+                *BAR.lock().unwrap() = Some(bar);
+            }
+            Ui::Tui => {
+                QUIET.store(true, Ordering::Relaxed);
+                execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+            }
+        }
+        Ok(progress)
+    }
+
+    // 得到了一条结果
+    pub fn record(&mut self, record: &Record) {
+        self.done += 1;
+        self.success += 1;
+        if !record.colo.is_empty() {
+            *self.colos.entry(record.colo.clone()).or_default() += 1;
+        }
+        let pos = self.top.partition_point(|r| r.delay <= record.delay);
+        if pos < TOP {
+            self.top.insert(pos, record.clone());
+            self.top.truncate(TOP);
+        }
+        self.update();
+    }
+
+    // 一个地址扫描失败
+    pub fn error(&mut self, reason: &str) {
+        self.done += 1;
+        *self.errors.entry(reason.to_string()).or_default() += 1;
+        self.update();
+    }
+
+    fn update(&mut self) {
+        match self.ui {
+            Ui::Plain => {}
+            Ui::Progress => {
+                if let Some(bar) = BAR.lock().unwrap().as_ref() {
+                    bar.set_position(self.done as u64);
+                    bar.set_message(
+                        format!(
+                            "{} {} {} {}",
+                            t("成功", "ok"),
+                            self.success,
+                            t("失败", "failed"),
+                            self.done - self.success
+                        )
+                    );
+                }
+            }
+            Ui::Tui => {
+                // 限制刷新的频率，最后一条结果一定刷新
+                let due = self.last_draw.map_or(true, |last| {
+                    last.elapsed() >= Duration::from_millis(100)
+                });
+                if due || self.done == self.total {
+                    let _ = self.draw();
+                    self.last_draw = Some(Instant::now());
+                }
+            }
+        }
+    }
+
+    fn draw(&self) -> io::Result<()> {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { (self.done as f64) / elapsed } else { 0.0 };
+        let eta = match rate > 0.0 {
+            true => Duration::from_secs_f64(((self.total - self.done) as f64) / rate),
+            false => Duration::ZERO,
+        };
+        let mut lines = vec![
+            format!(
+                "{} {}/{} ({:.1}%) | {:.1}/s | ETA {}s | {} {} | {} {}",
+                t("进度", "progress"),
+                self.done,
+                self.total,
+                ((self.done as f64) * 100.0) / (self.total.max(1) as f64),
+                rate,
+                eta.as_secs(),
+                t("成功", "ok"),
+                self.success,
+                t("失败", "failed"),
+                self.done - self.success
+            ),
+            String::new(),
+            format!("{} {}", t("最快的地址", "Fastest"), TOP),
+            format!("{:<40} {:<6} {:>10} {:>6}", "ip", "colo", "latency_ms", "status"),
+        ];
+        for record in &self.top {
+            lines.push(
+                format!(
+                    "{:<40} {:<6} {:>10} {:>6}",
+                    record.ip,
+                    record.colo,
+                    record.delay.as_millis(),
+                    record.http_status_code
+                )
+            );
+        }
+        if !self.colos.is_empty() {
+            let mut colos: Vec<(&String, &usize)> = self.colos.iter().collect();
+            colos.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            lines.push(String::new());
+            lines.push(t("各数据中心的数量", "Per colo").to_string());
+            for chunk in colos.chunks(8) {
+                lines.push(
+                    chunk
+                        .iter()
+                        .map(|(colo, count)| format!("{:<5}{:>5}", colo, count))
+                        .collect::<Vec<String>>()
+                        .join("  ")
+                );
+            }
+        }
+        if !self.errors.is_empty() {
+            lines.push(String::new());
+            lines.push(t("失败的原因", "Errors").to_string());
+            for (reason, count) in &self.errors {
+                lines.push(format!("{:>6}  {}", count, reason));
+            }
+        }
+        let mut out = io::stdout();
+        queue!(out, cursor::MoveTo(0, 0), terminal::Clear(terminal::ClearType::All))?;
+        for line in lines {
+            queue!(out, Print(line), cursor::MoveToNextLine(1))?;
+        }
+        out.flush()
+    }
+
+    // 扫描结束，收起进度条或退出全屏面板
+    pub fn finish(&mut self) {
+        if !self.active {
+            return;
+        }
+        self.active = false;
+        match self.ui {
+            Ui::Plain => {}
+            Ui::Progress => {
+                if let Some(bar) = BAR.lock().unwrap().take() {
+                    bar.finish();
+                }
+            }
+            Ui::Tui => {
+                let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+                QUIET.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// 出错提前返回时也要恢复终端
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
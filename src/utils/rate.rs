@@ -0,0 +1,108 @@
+use crate::utils::checkpoint::StopHandle;
+use crate::utils::i18n::t;
+use crate::utils::models::Target;
+
+use ipnetwork::IpNetwork;
+use std::{
+    collections::HashMap,
+    net::{ IpAddr, SocketAddr },
+    sync::Mutex,
+    time::{ Duration, Instant },
+};
+use url::Url;
+
+// 令牌桶：每秒补充rate个令牌，最多存capacity个，令牌不够时预约后面的令牌并等待
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, capacity: f64) -> Self {
+        TokenBucket { rate, capacity, tokens: capacity, last: Instant::now() }
+    }
+
+    // 取一个令牌，返回需要等待的时长
+    fn reserve(&mut self) -> Duration {
+        let now = Instant::now();
+        let refill = now.duration_since(self.last).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(self.capacity);
+        self.last = now;
+        self.tokens -= 1.0;
+        match self.tokens < 0.0 {
+            true => Duration::from_secs_f64(-self.tokens / self.rate),
+            false => Duration::ZERO,
+        }
+    }
+}
+
+// 目标所在的网段：IPv4取/24，IPv6取/48，域名就是域名本身
+fn prefix_key(target: &Target) -> String {
+    let addr = target.resolved_ip.as_deref().unwrap_or(&target.addr);
+    let ip = addr
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| addr.parse::<IpNetwork>().ok().map(|network| network.ip()))
+        .or_else(|| addr.parse::<SocketAddr>().ok().map(|socket| socket.ip()))
+        .or_else(|| {
+            let host = Url::parse(addr).ok()?.host_str()?.to_string();
+            host.trim_start_matches('[').trim_end_matches(']').parse().ok()
+        });
+    match ip {
+        Some(ip) => {
+            let prefix = if ip.is_ipv4() { 24 } else { 48 };
+            IpNetwork::new(ip, prefix)
+                .map(|network| format!("{}/{}", network.network(), prefix))
+                .unwrap_or_else(|_| addr.to_string())
+        }
+        None => addr.to_string(),
+    }
+}
+
+// 限速：--rate限制每秒开始扫描的地址总数，--per-prefix-rate限制同一个网段每秒开始扫描的地址数
+pub struct RateLimiter {
+    global: Option<Mutex<TokenBucket>>,
+    per_prefix: Option<(f64, Mutex<HashMap<String, TokenBucket>>)>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: Option<f64>, per_prefix_rate: Option<f64>) -> Self {
+        RateLimiter {
+            // 总的速率允许1秒的突发
+            global: rate.map(|rate| Mutex::new(TokenBucket::new(rate, rate.max(1.0)))),
+            per_prefix: per_prefix_rate.map(|rate| (rate, Mutex::new(HashMap::new()))),
+        }
+    }
+
+    // 扫描一个地址之前调用，超过速率就在当前线程等待；等待中停止了就提前返回false，不再扫描这个地址
+    pub fn wait(&self, target: &Target, stop: &StopHandle) -> bool {
+        if let Some((rate, buckets)) = &self.per_prefix {
+            let delay = buckets
+                .lock()
+                .unwrap()
+                .entry(prefix_key(target))
+                .or_insert_with(|| TokenBucket::new(*rate, 1.0))
+                .reserve();
+            if !stop.sleep(delay) {
+                return false;
+            }
+        }
+        if let Some(bucket) = &self.global {
+            let delay = bucket.lock().unwrap().reserve();
+            if !stop.sleep(delay) {
+                return false;
+            }
+        }
+        !stop.stopped()
+    }
+}
+
+// 速率必须是正数
+pub fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("{}: {}", t("无效的速率", "invalid rate"), s)),
+    }
+}
@@ -0,0 +1,443 @@
+use crate::utils::columns::{ sort_records, Column, SortKey };
+use crate::utils::files::ResultSink;
+use crate::utils::i18n::t;
+use crate::utils::models::{ Airport, Record };
+
+use std::{ collections::BTreeMap, error::Error, fs };
+
+// 页面的样式和表格排序的脚本都内嵌在文件中，不依赖任何外部的JS/CSS
+static STYLE: &str =
+    "body{font-family:sans-serif;margin:24px;color:#222}
+h1{font-size:22px}h2{font-size:17px;margin-top:32px}
+table{border-collapse:collapse;font-size:13px}
+th,td{border:1px solid #ddd;padding:4px 8px;text-align:left}
+th{background:#f3f3f3;cursor:pointer;user-select:none}
+tr:nth-child(even) td{background:#fafafa}
+svg text{font-size:11px;fill:#444}
+.meta{color:#666;font-size:13px}";
+
+static SCRIPT: &str =
+    "document.querySelectorAll('th').forEach(function(th){
+  th.addEventListener('click',function(){
+    var table=th.closest('table'),body=table.tBodies[0],i=th.cellIndex;
+    var asc=th.dataset.order!=='asc';th.dataset.order=asc?'asc':'desc';
+    var rows=Array.prototype.slice.call(body.rows);
+    rows.sort(function(a,b){
+      var x=a.cells[i].textContent,y=b.cells[i].textContent,nx=parseFloat(x),ny=parseFloat(y);
+      var r=(!isNaN(nx)&&!isNaN(ny))?nx-ny:x.localeCompare(y);
+      return asc?r:-r;
+    });
+    rows.forEach(function(row){body.appendChild(row);});
+  });
+});";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 延迟分布直方图，按最大延迟分成20个区间
+fn latency_histogram(records: &[Record]) -> String {
+    const BINS: usize = 20;
+    const WIDTH: f64 = 800.0;
+    const HEIGHT: f64 = 200.0;
+    let latencies: Vec<u64> = records
+        .iter()
+        .map(|record| record.delay.as_millis() as u64)
+        .collect();
+    let max = latencies.iter().copied().max().unwrap_or_default().max(1);
+    let step = max.div_ceil(BINS as u64).max(1);
+    let mut counts = [0usize; BINS];
+    for latency in latencies {
+        counts[((latency / step) as usize).min(BINS - 1)] += 1;
+    }
+    let peak = counts.iter().copied().max().unwrap_or_default().max(1) as f64;
+    let bar = WIDTH / (BINS as f64);
+    let mut svg = format!(
+        "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        WIDTH,
+        HEIGHT + 30.0,
+        WIDTH,
+        HEIGHT + 30.0
+    );
+    for (i, count) in counts.iter().enumerate() {
+        let h = ((*count as f64) / peak) * HEIGHT;
+        let x = (i as f64) * bar;
+        svg.push_str(
+            &format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#f38020\"><title>{}-{} ms: {}</title></rect>",
+                x + 1.0,
+                HEIGHT - h,
+                bar - 2.0,
+                h,
+                (i as u64) * step,
+                ((i as u64) + 1) * step,
+                count
+            )
+        );
+        if i % 4 == 0 {
+            svg.push_str(
+                &format!(
+                    "<text x=\"{:.1}\" y=\"{}\">{}</text>",
+                    x + 2.0,
+                    HEIGHT + 15.0,
+                    (i as u64) * step
+                )
+            );
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// 每个数据中心的地址数量，横向的条形图
+fn colo_bars(colos: &BTreeMap<String, usize>) -> String {
+    const WIDTH: f64 = 600.0;
+    const ROW: f64 = 18.0;
+    let mut colos: Vec<(&String, &usize)> = colos.iter().collect();
+    colos.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let peak = colos.first().map(|(_, count)| **count).unwrap_or(1) as f64;
+    let height = (colos.len() as f64) * ROW + 4.0;
+    let mut svg = format!("<svg width=\"{}\" height=\"{}\">", WIDTH + 120.0, height);
+    for (i, (colo, count)) in colos.iter().enumerate() {
+        let y = (i as f64) * ROW;
+        svg.push_str(
+            &format!(
+                "<text x=\"0\" y=\"{:.1}\">{}</text><rect x=\"60\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#4a90d9\"/><text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                y + 13.0,
+                escape(colo),
+                y + 2.0,
+                ((**count as f64) / peak) * WIDTH,
+                ROW - 4.0,
+                ((**count as f64) / peak) * WIDTH + 64.0,
+                y + 13.0,
+                count
+            )
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// 简化的世界海岸线，每块陆地是一个(经度, 纬度)的多边形，只用来在分布图中标出大致的位置
+static LANDMASSES: &[&[(f64, f64)]] = &[
+    // 北美洲
+    &[
+        (-168.0, 66.0), (-162.0, 70.0), (-156.0, 71.5), (-141.0, 69.7), (-128.0, 70.0), (-115.0, 68.0), (-95.0, 72.0),
+        (-82.0, 73.0), (-80.0, 69.0), (-90.0, 64.0), (-94.0, 59.0), (-85.0, 55.0), (-80.0, 52.0), (-79.0, 57.0),
+        (-77.0, 62.0), (-70.0, 59.0), (-62.0, 58.0), (-56.0, 52.0), (-60.0, 47.0), (-66.0, 45.0), (-70.0, 42.0),
+        (-74.0, 40.0), (-76.0, 37.0), (-76.0, 35.0), (-81.0, 31.0), (-80.0, 26.0), (-81.0, 25.5), (-83.0, 29.0),
+        (-86.0, 30.3), (-90.0, 29.5), (-94.0, 29.5), (-97.0, 27.0), (-97.5, 22.0), (-96.0, 19.0), (-92.0, 18.5),
+        (-90.5, 21.0), (-87.0, 21.5), (-88.0, 16.0), (-84.0, 15.5), (-83.5, 11.0), (-79.5, 9.0), (-77.5, 8.0),
+        (-80.0, 7.5), (-85.0, 10.0), (-87.0, 13.0), (-92.0, 14.5), (-96.0, 15.7), (-105.0, 19.5), (-106.0, 23.0),
+        (-109.0, 26.0), (-112.5, 29.5), (-114.7, 31.7), (-113.0, 29.0), (-111.5, 26.0), (-110.0, 23.0),
+        (-112.0, 24.5), (-114.0, 27.5), (-116.0, 30.0), (-117.0, 32.5), (-120.5, 34.5), (-124.0, 40.0),
+        (-124.5, 46.0), (-123.0, 49.0), (-128.0, 51.0), (-133.0, 55.0), (-136.0, 58.0), (-140.0, 60.0),
+        (-147.0, 61.0), (-152.0, 59.0), (-157.0, 57.0), (-164.0, 55.0), (-158.0, 58.0), (-162.0, 60.0),
+        (-166.0, 62.0), (-165.0, 64.5),
+    ],
+    // 格陵兰
+    &[
+        (-73.0, 78.0), (-60.0, 82.0), (-35.0, 83.5), (-20.0, 82.0), (-18.0, 77.0), (-22.0, 70.0), (-32.0, 68.0),
+        (-40.0, 65.0), (-43.0, 60.0), (-48.0, 61.0), (-52.0, 65.0), (-55.0, 70.0), (-58.0, 75.0), (-68.0, 76.0),
+    ],
+    // 巴芬岛
+    &[
+        (-90.0, 72.5), (-80.0, 73.5), (-72.0, 71.0), (-67.0, 69.5), (-62.0, 66.5), (-65.0, 62.5), (-72.0, 64.0),
+        (-77.0, 65.5), (-73.0, 67.5), (-80.0, 70.0),
+    ],
+    // 埃尔斯米尔岛
+    &[
+        (-90.0, 80.0), (-75.0, 83.0), (-62.0, 82.5), (-72.0, 78.5), (-80.0, 76.5), (-90.0, 77.5),
+    ],
+    // 维多利亚岛
+    &[
+        (-118.0, 73.0), (-105.0, 73.0), (-100.0, 70.0), (-112.0, 68.5), (-118.0, 70.0),
+    ],
+    // 古巴
+    &[
+        (-85.0, 21.8), (-82.0, 23.2), (-77.5, 22.5), (-74.2, 20.2), (-77.5, 19.8), (-80.0, 21.8),
+    ],
+    // 伊斯帕尼奥拉岛
+    &[
+        (-74.5, 19.8), (-70.0, 19.8), (-68.3, 18.6), (-71.5, 17.6), (-74.5, 18.4),
+    ],
+    // 南美洲
+    &[
+        (-77.5, 8.0), (-72.0, 11.5), (-64.0, 10.5), (-60.0, 8.5), (-52.0, 5.0), (-50.0, 0.0), (-44.0, -2.5),
+        (-35.0, -5.5), (-35.0, -9.0), (-39.0, -13.0), (-39.0, -18.0), (-41.0, -22.0), (-48.0, -26.0), (-53.0, -34.0),
+        (-58.0, -38.5), (-62.0, -39.0), (-65.0, -42.0), (-67.5, -46.5), (-69.0, -51.0), (-68.5, -54.5),
+        (-72.0, -53.0), (-75.0, -50.0), (-75.0, -44.0), (-73.5, -37.0), (-71.5, -30.0), (-70.5, -18.5),
+        (-76.0, -14.0), (-81.0, -5.5), (-80.0, -1.0), (-78.0, 2.0),
+    ],
+    // 冰岛
+    &[
+        (-24.0, 65.5), (-22.0, 66.4), (-15.0, 66.5), (-13.5, 65.0), (-18.0, 63.4), (-22.5, 63.8),
+    ],
+    // 大不列颠岛
+    &[
+        (-5.5, 50.0), (1.5, 51.0), (1.7, 52.7), (0.0, 53.5), (-2.0, 55.8), (-2.0, 57.7), (-3.0, 58.6), (-5.0, 58.6),
+        (-6.2, 56.5), (-5.0, 55.0), (-3.0, 54.5), (-3.0, 53.4), (-4.5, 52.5), (-5.0, 51.6), (-3.0, 51.3),
+    ],
+    // 爱尔兰岛
+    &[
+        (-10.0, 51.6), (-6.0, 52.2), (-6.0, 54.0), (-7.5, 55.3), (-10.0, 54.2),
+    ],
+    // 非洲
+    &[
+        (-17.0, 21.0), (-16.0, 28.0), (-10.0, 30.0), (-9.0, 32.5), (-6.0, 35.8), (0.0, 35.8), (10.0, 37.3),
+        (11.0, 33.5), (20.0, 30.5), (25.0, 32.0), (32.0, 31.2), (34.5, 28.0), (37.0, 22.0), (39.0, 16.0),
+        (43.0, 12.5), (51.0, 11.8), (51.0, 10.5), (48.0, 5.0), (41.0, -2.0), (39.0, -6.5), (40.5, -11.0),
+        (40.5, -15.0), (35.0, -20.0), (35.5, -24.0), (32.5, -28.5), (28.0, -33.0), (20.0, -34.8), (18.0, -32.0),
+        (15.0, -27.0), (12.0, -18.0), (13.5, -12.0), (12.0, -5.0), (9.0, -1.0), (9.5, 3.5), (6.0, 4.3), (2.0, 6.3),
+        (-4.0, 5.2), (-8.0, 4.4), (-13.0, 8.0), (-15.0, 11.0), (-17.5, 14.7), (-16.5, 19.0),
+    ],
+    // 马达加斯加
+    &[
+        (44.0, -25.0), (47.0, -25.0), (50.0, -15.5), (49.5, -12.0), (44.0, -16.0), (43.5, -22.0),
+    ],
+    // 亚欧大陆
+    &[
+        (-9.0, 43.0), (-1.5, 43.5), (-1.5, 46.5), (-4.5, 48.5), (-1.5, 49.5), (2.0, 51.0), (5.0, 53.5), (8.5, 54.0),
+        (8.5, 57.0), (10.5, 57.5), (10.5, 54.5), (13.0, 54.5), (18.0, 54.8), (21.0, 55.0), (21.0, 57.0), (24.0, 57.5),
+        (24.0, 59.5), (28.0, 60.0), (23.0, 60.0), (21.0, 62.0), (25.5, 65.0), (22.0, 65.8), (17.0, 61.0),
+        (18.5, 59.5), (16.0, 56.3), (12.8, 55.5), (11.0, 59.0), (8.0, 58.0), (5.0, 59.0), (5.0, 62.0), (10.0, 64.0),
+        (14.0, 67.5), (18.0, 70.0), (25.0, 71.0), (31.0, 70.0), (40.0, 67.0), (33.0, 66.5), (41.0, 64.0),
+        (44.0, 68.0), (53.0, 68.5), (59.0, 69.0), (68.0, 68.5), (72.0, 72.5), (80.0, 73.5), (87.0, 75.0),
+        (100.0, 77.5), (105.0, 77.5), (113.0, 73.5), (130.0, 71.0), (142.0, 72.5), (160.0, 70.0), (170.0, 70.0),
+        (180.0, 69.0), (180.0, 65.0), (177.0, 62.5), (170.0, 60.0), (163.0, 58.5), (160.0, 53.0), (156.5, 51.0),
+        (156.0, 57.0), (150.0, 59.5), (142.0, 59.0), (136.0, 54.5), (140.0, 53.0), (140.0, 48.0), (135.0, 43.0),
+        (130.0, 42.5), (129.0, 35.5), (126.5, 34.5), (126.0, 37.5), (125.0, 39.5), (121.0, 40.8), (122.0, 37.0),
+        (119.0, 37.0), (121.5, 32.0), (122.0, 30.0), (119.5, 25.5), (116.5, 23.0), (111.0, 21.5), (110.0, 20.3),
+        (108.0, 21.5), (106.0, 18.0), (109.0, 12.0), (107.0, 10.5), (105.0, 8.7), (102.5, 12.5), (100.0, 13.5),
+        (100.5, 8.0), (103.5, 1.3), (101.0, 3.0), (98.5, 8.0), (98.3, 15.0), (97.0, 17.0), (94.5, 16.3), (94.0, 19.5),
+        (92.0, 22.0), (90.0, 22.0), (87.0, 21.5), (86.0, 19.5), (80.3, 15.5), (80.0, 10.0), (77.5, 8.0), (76.0, 11.0),
+        (73.5, 16.0), (72.8, 21.0), (70.0, 21.0), (67.0, 24.5), (61.5, 25.2), (57.0, 25.5), (56.5, 27.0),
+        (52.0, 27.5), (48.5, 30.0), (50.0, 26.5), (51.5, 24.0), (56.0, 26.0), (59.5, 22.5), (57.0, 18.8),
+        (52.0, 16.0), (45.0, 13.0), (43.0, 13.0), (40.0, 17.5), (39.0, 21.5), (35.0, 28.0), (32.5, 30.0),
+        (34.5, 31.5), (35.8, 35.5), (36.0, 36.8), (30.0, 36.2), (27.0, 37.0), (26.0, 40.0), (23.0, 40.5),
+        (24.0, 38.0), (22.0, 36.5), (21.0, 39.0), (19.5, 41.5), (13.5, 45.5), (12.3, 44.5), (16.0, 41.5),
+        (18.5, 40.2), (16.0, 38.0), (15.5, 40.0), (12.0, 42.0), (10.0, 44.0), (7.5, 43.8), (3.0, 43.3), (3.2, 42.0),
+        (0.0, 39.5), (-0.5, 38.0), (-2.0, 36.7), (-5.5, 36.0), (-6.5, 37.0), (-9.0, 37.0), (-8.8, 41.0),
+    ],
+    // 斯里兰卡
+    &[
+        (79.8, 9.7), (81.8, 7.5), (81.0, 6.0), (80.0, 6.0),
+    ],
+    // 日本
+    &[
+        (130.0, 31.0), (131.5, 31.5), (132.0, 34.0), (135.0, 33.5), (140.0, 35.0), (141.0, 38.0), (142.0, 40.5),
+        (141.0, 41.5), (145.5, 43.3), (142.0, 45.5), (140.0, 43.0), (140.0, 40.5), (139.5, 38.0), (136.5, 37.0),
+        (133.0, 35.5), (130.5, 34.0),
+    ],
+    // 菲律宾
+    &[
+        (120.0, 18.5), (122.5, 18.3), (124.0, 12.5), (126.5, 7.0), (125.0, 6.0), (122.0, 7.0), (121.5, 12.0),
+        (120.0, 14.5),
+    ],
+    // 加里曼丹岛
+    &[
+        (109.0, 1.5), (111.0, 2.5), (115.0, 5.0), (117.0, 7.0), (119.0, 5.0), (118.0, 1.0), (116.0, -3.5),
+        (114.0, -3.5), (110.0, -3.0),
+    ],
+    // 苏门答腊岛
+    &[
+        (95.5, 5.5), (98.0, 4.0), (104.0, -1.0), (106.0, -6.0), (104.5, -5.5), (101.0, -2.0), (98.5, 1.0),
+    ],
+    // 爪哇岛
+    &[
+        (105.5, -6.8), (108.0, -6.3), (112.0, -6.8), (114.5, -7.8), (110.0, -8.2), (106.0, -7.5),
+    ],
+    // 新几内亚岛
+    &[
+        (131.0, -1.3), (134.0, -0.8), (138.0, -1.7), (141.0, -2.6), (146.0, -5.0), (148.0, -6.0), (150.5, -10.5),
+        (147.0, -10.0), (143.5, -9.0), (141.0, -9.0), (138.0, -8.3), (137.5, -5.0), (132.5, -4.0),
+    ],
+    // 澳大利亚
+    &[
+        (114.0, -22.0), (114.2, -26.5), (115.0, -34.0), (118.0, -35.0), (123.5, -33.8), (129.0, -31.5),
+        (132.0, -32.0), (135.5, -34.8), (138.0, -35.5), (140.0, -38.0), (144.0, -38.3), (146.5, -39.0),
+        (150.0, -37.5), (151.5, -33.0), (153.5, -28.5), (153.0, -25.0), (150.5, -22.5), (146.0, -19.0),
+        (145.5, -15.0), (143.5, -14.0), (142.5, -10.7), (141.5, -13.0), (141.5, -17.0), (140.0, -17.5),
+        (136.0, -15.0), (137.0, -12.0), (132.5, -11.5), (130.0, -13.0), (126.5, -14.0), (122.0, -17.5),
+        (121.0, -19.5), (117.0, -20.7),
+    ],
+    // 塔斯马尼亚岛
+    &[
+        (144.5, -40.7), (148.3, -40.9), (148.0, -43.2), (146.0, -43.6),
+    ],
+    // 新西兰北岛
+    &[
+        (172.7, -34.4), (175.0, -36.8), (178.5, -37.7), (177.0, -39.5), (175.0, -41.5), (174.6, -39.8),
+        (173.8, -39.2), (174.5, -37.0),
+    ],
+    // 新西兰南岛
+    &[
+        (172.7, -40.5), (174.3, -41.7), (173.0, -43.5), (171.0, -44.5), (169.0, -46.6), (166.5, -46.0),
+        (168.0, -44.0), (170.5, -42.5),
+    ],
+    // 南极洲
+    &[
+        (-180.0, -90.0), (-180.0, -78.0), (-160.0, -78.0), (-150.0, -76.5), (-135.0, -74.5), (-120.0, -73.5),
+        (-100.0, -73.0), (-80.0, -73.0), (-75.0, -70.0), (-66.0, -68.0), (-58.0, -63.5), (-60.0, -64.5),
+        (-62.0, -66.0), (-62.0, -70.0), (-60.0, -74.0), (-40.0, -78.0), (-30.0, -77.0), (-20.0, -73.0),
+        (-10.0, -71.0), (0.0, -70.0), (20.0, -70.0), (40.0, -69.0), (55.0, -66.5), (70.0, -68.0), (80.0, -67.0),
+        (100.0, -66.0), (120.0, -66.5), (140.0, -66.5), (150.0, -68.5), (165.0, -71.0), (170.0, -72.0),
+        (165.0, -78.0), (180.0, -78.0), (180.0, -90.0),
+    ],
+];
+
+// 数据中心的分布图，使用locations.json中的经纬度按等距圆柱投影画点，点的大小表示地址数量
+fn colo_map(colos: &BTreeMap<String, usize>, airports: &[Airport]) -> String {
+    const WIDTH: f64 = 800.0;
+    const HEIGHT: f64 = 400.0;
+    let project = |lat: f64, lon: f64| (((lon + 180.0) / 360.0) * WIDTH, ((90.0 - lat) / 180.0) * HEIGHT);
+    let mut svg = format!(
+        "<svg width=\"{}\" height=\"{}\"><rect width=\"{}\" height=\"{}\" fill=\"#eef4fa\" stroke=\"#ccc\"/>",
+        WIDTH,
+        HEIGHT,
+        WIDTH,
+        HEIGHT
+    );
+    // 经纬网，每30度一条线
+    for lon in (-150..=150).step_by(30) {
+        let (x, _) = project(0.0, lon as f64);
+        svg.push_str(
+            &format!("<line x1=\"{:.1}\" y1=\"0\" x2=\"{:.1}\" y2=\"{}\" stroke=\"#d5e0ea\"/>", x, x, HEIGHT)
+        );
+    }
+    for lat in (-60..=60).step_by(30) {
+        let (_, y) = project(lat as f64, 0.0);
+        svg.push_str(
+            &format!("<line x1=\"0\" y1=\"{:.1}\" x2=\"{}\" y2=\"{:.1}\" stroke=\"#d5e0ea\"/>", y, WIDTH, y)
+        );
+    }
+    // 陆地的轮廓，全部多边形放在一个path中
+    let mut land = String::new();
+    for polygon in LANDMASSES {
+        for (i, &(lon, lat)) in polygon.iter().enumerate() {
+            let (x, y) = project(lat, lon);
+            land.push_str(&format!("{}{:.1},{:.1}", if i == 0 { "M" } else { "L" }, x, y));
+        }
+        land.push('Z');
+    }
+    svg.push_str(&format!("<path d=\"{}\" fill=\"#dde5d4\" stroke=\"#b9c6ad\" stroke-width=\"0.6\"/>", land));
+    let peak = colos.values().copied().max().unwrap_or(1) as f64;
+    for (colo, count) in colos {
+        if let Some(airport) = airports.iter().find(|a| &a.iata == colo) {
+            let (x, y) = project(airport.lat, airport.lon);
+            svg.push_str(
+                &format!(
+                    "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"#f38020\" fill-opacity=\"0.7\"><title>{} {}: {}</title></circle>",
+                    x,
+                    y,
+                    3.0 + ((*count as f64) / peak).sqrt() * 9.0,
+                    escape(colo),
+                    escape(&airport.city),
+                    count
+                )
+            );
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn table(records: &[Record], columns: &[Column]) -> String {
+    let mut html = String::from("<table><thead><tr>");
+    for column in columns {
+        html.push_str(&format!("<th>{}</th>", escape(column.header())));
+    }
+    html.push_str("</tr></thead><tbody>");
+    for record in records {
+        html.push_str("<tr>");
+        for column in columns {
+            html.push_str(&format!("<td>{}</td>", escape(&column.value(record))));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+    html
+}
+
+// 生成单个静态的HTML报告：可排序的结果表格、延迟直方图、数据中心条形图和分布图
+pub fn write_report(
+    path: &str,
+    records: &[Record],
+    columns: &[Column],
+    airports: &[Airport]
+) -> Result<(), Box<dyn Error>> {
+    let mut colos: BTreeMap<String, usize> = BTreeMap::new();
+    for record in records.iter().filter(|record| !record.colo.is_empty()) {
+        *colos.entry(record.colo.clone()).or_default() += 1;
+    }
+    let title = t("扫描报告", "Scan report");
+    let mut html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head><body>",
+        title,
+        STYLE
+    );
+    html.push_str(
+        &format!(
+            "<h1>{}</h1><p class=\"meta\">{}: {} | {}: {}</p>",
+            title,
+            t("生成时间", "Generated"),
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            t("结果数量", "Results"),
+            records.len()
+        )
+    );
+    html.push_str(&format!("<h2>{}</h2>", t("延迟分布(毫秒)", "Latency histogram (ms)")));
+    html.push_str(&latency_histogram(records));
+    if !colos.is_empty() {
+        html.push_str(&format!("<h2>{}</h2>", t("各数据中心的地址数量", "Addresses per colo")));
+        html.push_str(&colo_bars(&colos));
+        html.push_str(&format!("<h2>{}</h2>", t("数据中心分布", "Colo map")));
+        html.push_str(&colo_map(&colos, airports));
+    }
+    html.push_str(
+        &format!("<h2>{}</h2>", t("扫描结果(点击标题排序)", "Results (click a header to sort)"))
+    );
+    html.push_str(&table(records, columns));
+    html.push_str(&format!("<script>{}</script></body></html>", SCRIPT));
+    fs::write(path, html)?;
+    Ok(())
+}
+
+// HTML报告：收集通过过滤的结果，扫描结束后排序生成报告
+pub struct ReportSink {
+    path: String,
+    columns: Vec<Column>,
+    sort_by: Vec<SortKey>,
+    airports: Vec<Airport>,
+    records: Vec<Record>,
+}
+
+impl ReportSink {
+    pub fn new(
+        path: &str,
+        columns: Vec<Column>,
+        sort_by: Vec<SortKey>,
+        airports: Vec<Airport>
+    ) -> Self {
+        ReportSink { path: path.to_string(), columns, sort_by, airports, records: Vec::new() }
+    }
+}
+
+impl ResultSink for ReportSink {
+    fn open(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), Box<dyn Error>> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        sort_records(&mut self.records, &self.sort_by);
+        write_report(&self.path, &self.records, &self.columns, &self.airports)
+    }
+}
@@ -0,0 +1,307 @@
+use crate::utils::cdn::{ self, CdnDetector };
+use crate::utils::checkpoint::StopHandle;
+use crate::utils::columns::Filter;
+use crate::utils::files::ResultSink;
+use crate::utils::fingerprint::{ self, Rule };
+use crate::utils::models::{ Airport, Record, ScanMode, Target };
+use crate::utils::prober::{ CurlProber, Prober };
+use crate::utils::rate::RateLimiter;
+use crate::utils::timeout::TimeoutPolicy;
+use crate::utils::{ curl, jetbrains };
+
+use openssl::x509::store::X509Store;
+use std::{ error::Error, sync::{ mpsc, Arc } };
+use threadpool::ThreadPool;
+
+/// 扫描器：设置好目标、探测参数、数据中心位置和结果的输出，调用[`Scanner::scan`]开始扫描。
+///
+/// ```no_run
+/// use curl_cdn_cgi_trace_rust::{ Scanner, ScanMode, Target };
+///
+/// let targets = vec![Target::from("104.16.0.1".to_string())];
+/// let mut scan = Scanner::new(targets).mode(ScanMode::Cdn).pool(10).scan()?;
+/// for (_, result) in scan.by_ref() {
+///     if let Ok(record) = result {
+///         println!("{} {} {:?}", record.ip, record.colo, record.delay);
+///     }
+/// }
+/// scan.finish()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Scanner {
+    targets: Vec<Target>,
+    mode: ScanMode,
+    pool: usize,
+    rules: Option<Vec<Rule>>,
+    detectors: Option<Vec<Box<dyn CdnDetector>>>,
+    prober: Option<Box<dyn Prober>>,
+    airports: Vec<Airport>,
+    timeouts: TimeoutPolicy,
+    trusted_certs: Option<X509Store>,
+    stop: StopHandle,
+    rate: Option<f64>,
+    per_prefix_rate: Option<f64>,
+    filters: Vec<Filter>,
+    sinks: Vec<Box<dyn ResultSink>>,
+}
+
+impl Scanner {
+    /// 扫描这些目标，默认cdn-cgi/trace模式，50个线程
+    pub fn new(targets: Vec<Target>) -> Self {
+        Scanner {
+            targets,
+            mode: ScanMode::Cdn,
+            pool: 50,
+            rules: None,
+            detectors: None,
+            prober: None,
+            airports: Vec::new(),
+            timeouts: TimeoutPolicy::default(),
+            trusted_certs: None,
+            stop: StopHandle::new(),
+            rate: None,
+            per_prefix_rate: None,
+            filters: Vec::new(),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// 扫描模式
+    pub fn mode(mut self, mode: ScanMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// 同时扫描的线程数量
+    pub fn pool(mut self, pool: usize) -> Self {
+        self.pool = pool.max(1);
+        self
+    }
+
+    /// 指纹规则，不设置时指纹模式使用内置的规则，jetbrains模式使用内置的JetBrains规则
+    pub fn rules(mut self, rules: Vec<Rule>) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// 识别CDN的检测器，不设置时使用内置的检测器和cloudflare-ranges.txt
+    pub fn detectors(mut self, detectors: Vec<Box<dyn CdnDetector>>) -> Self {
+        self.detectors = Some(detectors);
+        self
+    }
+
+    /// 发送探测请求的后端，不设置时使用curl
+    pub fn prober(mut self, prober: Box<dyn Prober>) -> Self {
+        self.prober = Some(prober);
+        self
+    }
+
+    /// 数据中心的位置(locations.json)，用来补全地区和城市
+    pub fn airports(mut self, airports: Vec<Airport>) -> Self {
+        self.airports = airports;
+        self
+    }
+
+    /// 探测请求的超时和重试策略，不设置时连接超时3秒、总超时10秒、不重试
+    pub fn timeouts(mut self, policy: TimeoutPolicy) -> Self {
+        self.timeouts = policy;
+        self
+    }
+
+    /// jetbrains模式下校验响应签名使用的根证书，不设置时只检查握手，签名验证的结果都是false
+    pub fn trusted_certs(mut self, certs: X509Store) -> Self {
+        self.trusted_certs = Some(certs);
+        self
+    }
+
+    /// 停止扫描的开关，关闭后不再开始新的扫描；不设置时可以用[`Scan::stop_handle`]取得
+    pub fn stop_handle(mut self, stop: StopHandle) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// 每秒开始扫描的地址总数
+    pub fn rate(mut self, rate: Option<f64>) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// 同一个网段(IPv4 /24、IPv6 /48)每秒开始扫描的地址数
+    pub fn per_prefix_rate(mut self, rate: Option<f64>) -> Self {
+        self.per_prefix_rate = rate;
+        self
+    }
+
+    /// 只把满足全部条件的结果写入输出
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// 结果的输出，可以添加多个，扫描时逐条写入
+    pub fn sink(mut self, sink: Box<dyn ResultSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    // 没有设置规则时，按模式选择内置的规则
+    fn resolve_rules(&mut self) -> Option<Arc<Vec<Rule>>> {
+        let rules = match (self.mode, self.rules.take()) {
+            (ScanMode::Cdn, _) => {
+                return None;
+            }
+            (_, Some(rules)) => rules,
+            (ScanMode::Jetbrains, None) =>
+                fingerprint
+                    ::builtin_rules()
+                    .into_iter()
+                    .filter(|rule| rule.name == fingerprint::JETBRAINS_RULE)
+                    .collect(),
+            (ScanMode::Fingerprint, None) => fingerprint::builtin_rules(),
+        };
+        Some(Arc::new(rules))
+    }
+
+    /// 打开全部输出并开始扫描，返回按完成顺序产生结果的[`Scan`]
+    pub fn scan(mut self) -> Result<Scan, Box<dyn Error>> {
+        for sink in self.sinks.iter_mut() {
+            sink.open()?;
+        }
+        let mode = self.mode;
+        let rules = self.resolve_rules();
+        let detectors = Arc::new(
+            self.detectors.take().unwrap_or_else(|| cdn::builtin_detectors(cdn::CLOUDFLARE_RANGES))
+        );
+        let prober: Arc<dyn Prober> = match self.prober.take() {
+            Some(prober) => Arc::from(prober),
+            None => Arc::new(CurlProber::new()),
+        };
+        let airports = Arc::new(self.airports);
+        self.timeouts.stop_handle(self.stop.clone());
+        let timeouts = Arc::new(self.timeouts);
+        let trusted_certs = Arc::new(self.trusted_certs.take());
+        let limiter = Arc::new(RateLimiter::new(self.rate, self.per_prefix_rate));
+        let targets = Arc::new(self.targets);
+
+        let (tx_method, rx_method) = mpsc::channel();
+        let pool_method = ThreadPool::new(self.pool);
+        for index in 0..targets.len() {
+            let tx_method = tx_method.clone();
+            let targets = Arc::clone(&targets);
+            let airports = Arc::clone(&airports);
+            let detectors = Arc::clone(&detectors);
+            let prober = Arc::clone(&prober);
+            let rules = rules.clone();
+            let limiter = Arc::clone(&limiter);
+            let timeouts = Arc::clone(&timeouts);
+            let trusted_certs = Arc::clone(&trusted_certs);
+            let stop = self.stop.clone();
+            pool_method.execute(move || {
+                let target = &targets[index];
+                // 中断后不再开始新的扫描，正在等待限速的也不再等待
+                if stop.stopped() || !limiter.wait(target, &stop) {
+                    return;
+                }
+                let prober = prober.as_ref();
+                let result = match rules {
+                    Some(rules) if mode == ScanMode::Jetbrains =>
+                        jetbrains::run_jetbrains_check(target, &rules, prober, &timeouts, trusted_certs.as_ref().as_ref()),
+                    Some(rules) => curl::run_fingerprint_rules(target, &rules, prober, &timeouts),
+                    None => curl::run_command_and_process_data(target, &airports, &detectors, prober, &timeouts),
+                };
+                let _ = tx_method.send((index, result.map_err(|e| e.to_string())));
+            });
+        }
+
+        Ok(Scan {
+            completed: vec![false; targets.len()],
+            targets,
+            receiver: rx_method,
+            filters: self.filters,
+            sinks: self.sinks,
+            stop: self.stop,
+            error: None,
+        })
+    }
+}
+
+/// 正在进行的扫描，迭代得到(目标的序号, 结果)，失败的结果是错误原因。
+///
+/// 成功的结果补上扫描时间，满足过滤条件的写入全部输出；迭代结束后调用[`Scan::finish`]。
+pub struct Scan {
+    targets: Arc<Vec<Target>>,
+    receiver: mpsc::Receiver<(usize, Result<Record, String>)>,
+    completed: Vec<bool>,
+    filters: Vec<Filter>,
+    sinks: Vec<Box<dyn ResultSink>>,
+    stop: StopHandle,
+    error: Option<Box<dyn Error>>,
+}
+
+/// 扫描结束后，已经扫描和没有扫描(被中断)的目标
+pub struct Finished {
+    pub completed: Vec<Target>,
+    pub remaining: Vec<Target>,
+}
+
+impl Scan {
+    /// 目标的数量
+    pub fn total(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// 停止扫描的开关，关闭后剩下的目标不再扫描，[`Scan::finish`]返回它们
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    // 记录完成的目标，成功的结果写入输出，写入失败就停止扫描
+    fn handle(&mut self, (index, result): (usize, Result<Record, String>)) -> Option<(usize, Result<Record, String>)> {
+        self.completed[index] = true;
+        let result = result.map(|mut record| {
+            record.scanned_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            record
+        });
+        if let Ok(record) = &result {
+            if self.filters.iter().all(|filter| filter.matches(record)) {
+                for sink in self.sinks.iter_mut() {
+                    if let Err(e) = sink.write_record(record) {
+                        self.error = Some(e);
+                        return None;
+                    }
+                }
+            }
+        }
+        Some((index, result))
+    }
+
+    /// 关闭全部输出，返回已经扫描和剩下的目标；扫描中写入输出失败时返回这个错误
+    pub fn finish(mut self) -> Result<Finished, Box<dyn Error>> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        for sink in self.sinks.iter_mut() {
+            sink.finish()?;
+        }
+        let mut finished = Finished { completed: Vec::new(), remaining: Vec::new() };
+        for (target, done) in self.targets.iter().zip(&self.completed) {
+            match done {
+                true => finished.completed.push(target.clone()),
+                false => finished.remaining.push(target.clone()),
+            }
+        }
+        Ok(finished)
+    }
+}
+
+impl Iterator for Scan {
+    type Item = (usize, Result<Record, String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        let item = self.receiver.recv().ok()?;
+        self.handle(item)
+    }
+}
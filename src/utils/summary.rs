@@ -0,0 +1,199 @@
+use crate::utils::columns::Column;
+use crate::utils::files::{ write_to_csv, ResultSink };
+use crate::utils::i18n::t;
+use crate::utils::models::Record;
+
+use serde::Serialize;
+use std::{ collections::BTreeMap, error::Error, fs::File, io::{ BufWriter, Write } };
+
+// 汇总报告的分组方式
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryBy {
+    Colo,
+    Country,
+    Region,
+}
+
+impl SummaryBy {
+    fn column(self) -> Column {
+        match self {
+            SummaryBy::Colo => Column::Colo,
+            SummaryBy::Country => Column::Country,
+            SummaryBy::Region => Column::Region,
+        }
+    }
+}
+
+// 汇总报告的一行
+#[derive(Serialize, Debug)]
+pub struct SummaryRow {
+    pub group: String,
+    pub count: usize,
+    pub success: usize, // HTTP状态码为2xx的数量
+    pub success_rate: f64,
+    pub best_ms: u64,
+    pub median_ms: u64,
+    pub statuses: BTreeMap<String, usize>, // 状态码 => 数量
+}
+
+impl SummaryRow {
+    fn statuses_text(&self) -> String {
+        self.statuses
+            .iter()
+            .map(|(status, count)| format!("{}:{}", status, count))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+fn median(sorted: &[u64]) -> u64 {
+    match sorted.len() {
+        0 => 0,
+        n if n % 2 == 0 => (sorted[n / 2 - 1] + sorted[n / 2]) / 2,
+        n => sorted[n / 2],
+    }
+}
+
+// 按分组统计数量、成功率、最低和中位延迟、状态码分布，数量多的分组排在前面
+pub fn summarize(records: &[Record], by: SummaryBy) -> Vec<SummaryRow> {
+    let mut groups: BTreeMap<String, Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        let group = by.column().value(record);
+        let group = if group.is_empty() { "-".to_string() } else { group };
+        groups.entry(group).or_default().push(record);
+    }
+    let mut rows: Vec<SummaryRow> = groups
+        .into_iter()
+        .map(|(group, records)| {
+            let mut latencies: Vec<u64> = records
+                .iter()
+                .map(|record| record.delay.as_millis() as u64)
+                .collect();
+            latencies.sort();
+            let mut statuses: BTreeMap<String, usize> = BTreeMap::new();
+            for record in records.iter() {
+                let status = match record.http_status_code.is_empty() {
+                    true => "-".to_string(),
+                    false => record.http_status_code.clone(),
+                };
+                *statuses.entry(status).or_default() += 1;
+            }
+            let success = records
+                .iter()
+                .filter(|record| record.http_status_code.starts_with('2'))
+                .count();
+            SummaryRow {
+                group,
+                count: records.len(),
+                success,
+                success_rate: (success as f64) / (records.len() as f64),
+                best_ms: latencies.first().copied().unwrap_or_default(),
+                median_ms: median(&latencies),
+                statuses,
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.group.cmp(&b.group)));
+    rows
+}
+
+pub fn print_summary(rows: &[SummaryRow], by: SummaryBy, results: usize, total: usize) {
+    println!(
+        "\n{}: {}/{}",
+        t("结果数量/扫描的地址数量", "results/scanned"),
+        results,
+        total
+    );
+    if rows.is_empty() {
+        return;
+    }
+    println!(
+        "{:<16} {:>6} {:>8} {:>8} {:>10}  {}",
+        by.column().header(),
+        t("数量", "count"),
+        t("成功率", "success"),
+        "best_ms",
+        "median_ms",
+        t("状态码", "statuses")
+    );
+    for row in rows {
+        println!(
+            "{:<16} {:>6} {:>7.1}% {:>8} {:>10}  {}",
+            row.group,
+            row.count,
+            row.success_rate * 100.0,
+            row.best_ms,
+            row.median_ms,
+            row.statuses_text()
+        );
+    }
+}
+
+// 汇总报告写入文件，扩展名是.json就输出JSON，否则输出CSV
+pub fn write_summary(path: &str, rows: &[SummaryRow], by: SummaryBy) -> Result<(), Box<dyn Error>> {
+    if path.to_lowercase().ends_with(".json") {
+        let mut wtr = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut wtr, rows)?;
+        wtr.flush()?;
+        return Ok(());
+    }
+    let mut csv_rows = vec![
+        vec![
+            by.column().header().to_string(),
+            t("数量", "Count").to_string(),
+            t("成功数量", "Success").to_string(),
+            t("成功率", "Success Rate").to_string(),
+            t("最低延迟(毫秒)", "Best (ms)").to_string(),
+            t("中位延迟(毫秒)", "Median (ms)").to_string(),
+            t("状态码分布", "Status Codes").to_string()
+        ]
+    ];
+    csv_rows.extend(
+        rows.iter().map(|row| {
+            vec![
+                row.group.clone(),
+                row.count.to_string(),
+                row.success.to_string(),
+                format!("{:.3}", row.success_rate),
+                row.best_ms.to_string(),
+                row.median_ms.to_string(),
+                row.statuses_text()
+            ]
+        })
+    );
+    write_to_csv(path, csv_rows)
+}
+
+// 汇总：收集通过过滤的结果，扫描结束后打印汇总表格，指定了文件就同时写入文件
+pub struct SummarySink {
+    by: SummaryBy,
+    path: Option<String>,
+    total: usize,
+    records: Vec<Record>,
+}
+
+impl SummarySink {
+    pub fn new(by: SummaryBy, path: Option<String>, total: usize) -> Self {
+        SummarySink { by, path, total, records: Vec::new() }
+    }
+}
+
+impl ResultSink for SummarySink {
+    fn open(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), Box<dyn Error>> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        let rows = summarize(&self.records, self.by);
+        print_summary(&rows, self.by, self.records.len(), self.total);
+        if let Some(path) = &self.path {
+            write_summary(path, &rows, self.by)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use crate::utils::checkpoint::StopHandle;
+use crate::utils::i18n::t;
+
+use std::{ collections::VecDeque, sync::Mutex, time::Duration };
+
+// 自适应超时至少需要的样本数量，以及最多保留的最近样本数量
+const MIN_SAMPLES: usize = 20;
+const MAX_SAMPLES: usize = 1000;
+// 自适应超时不低于这个值，避免个别很快的响应把超时压得太低
+const ADAPTIVE_FLOOR: Duration = Duration::from_millis(500);
+// --retries允许的最大值
+pub const MAX_RETRIES: u32 = 10;
+
+// 探测请求的超时和重试策略，每次扫描使用自己的策略，自适应超时的样本也只来自这次扫描
+pub struct TimeoutPolicy {
+    pub connect: Duration, // 连接超时(--connect-timeout)
+    pub total: Duration, // 整个请求的超时(--max-time)
+    pub retries: u32, // 超时、连接被重置等临时错误的重试次数
+    pub backoff: Duration, // 第一次重试前等待的时长，之后每次翻倍，最多等待total
+    pub adaptive: bool, // 样本足够后，超时收紧到p90延迟的3倍
+    samples: Mutex<VecDeque<Duration>>, // 最近成功请求的耗时
+    stop: StopHandle, // 扫描停止后不再重试
+}
+
+impl TimeoutPolicy {
+    pub fn new(connect: Duration, total: Duration, retries: u32, adaptive: bool) -> Self {
+        TimeoutPolicy {
+            connect,
+            total,
+            retries,
+            backoff: Duration::from_millis(200),
+            adaptive,
+            samples: Mutex::new(VecDeque::new()),
+            stop: StopHandle::new(),
+        }
+    }
+
+    // 记录一次成功请求的耗时
+    pub fn record(&self, elapsed: Duration) {
+        if !self.adaptive {
+            return;
+        }
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed);
+    }
+
+    fn adaptive_limit(&self) -> Option<Duration> {
+        if !self.adaptive {
+            return None;
+        }
+        let mut samples: Vec<Duration> = self.samples.lock().unwrap().iter().copied().collect();
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        samples.sort();
+        let p90 = samples[((samples.len() * 9) / 10).min(samples.len() - 1)];
+        Some((p90 * 3).max(ADAPTIVE_FLOOR))
+    }
+
+    // 当前使用的(连接超时, 总超时)，自适应的值不会超过配置的值
+    pub fn current(&self) -> (Duration, Duration) {
+        match self.adaptive_limit() {
+            Some(limit) => (self.connect.min(limit), self.total.min(limit)),
+            None => (self.connect, self.total),
+        }
+    }
+
+    // 重试时检查的停止开关，扫描开始时设置为扫描的开关
+    pub fn stop_handle(&mut self, stop: StopHandle) {
+        self.stop = stop;
+    }
+
+    // 第attempt次重试前等待的时长，不超过总超时
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(attempt)).min(self.total)
+    }
+
+    // 第attempt次请求遇到临时错误后是否重试：还有重试次数时等待退避时间，等待中扫描停止则不再重试
+    pub fn retry(&self, attempt: u32) -> bool {
+        attempt < self.retries && !self.stop.stopped() && self.stop.sleep(self.backoff(attempt))
+    }
+}
+
+impl Default for TimeoutPolicy {
+    fn default() -> Self {
+        TimeoutPolicy::new(Duration::from_secs(3), Duration::from_secs(10), 0, false)
+    }
+}
+
+// 解析秒数，支持小数，如0.5
+pub fn parse_seconds(s: &str) -> Result<Duration, String> {
+    match s.parse::<f64>() {
+        Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(Duration::from_secs_f64(secs)),
+        _ => Err(format!("{}: {}", t("无效的秒数", "invalid seconds"), s)),
+    }
+}
+
+// 解析重试次数，不超过MAX_RETRIES
+pub fn parse_retries(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(retries) if retries <= MAX_RETRIES => Ok(retries),
+        _ => Err(format!("{}: {} (0-{})", t("无效的重试次数", "invalid retries"), s, MAX_RETRIES)),
+    }
+}
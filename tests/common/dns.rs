@@ -0,0 +1,60 @@
+// 本地DNS服务器的替身：UDP，A记录按名称返回设置的IPv4地址，其它查询返回没有记录的响应
+use std::{ collections::HashMap, net::{ Ipv4Addr, SocketAddr, UdpSocket }, thread };
+
+pub struct DnsStub {
+    addr: SocketAddr,
+}
+
+impl DnsStub {
+    pub fn start(records: Vec<(&str, Ipv4Addr)>) -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").expect("bind dns stub");
+        let addr = socket.local_addr().unwrap();
+        let records: HashMap<String, Ipv4Addr> = records
+            .into_iter()
+            .map(|(name, ip)| (name.trim_end_matches('.').to_ascii_lowercase(), ip))
+            .collect();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+                if let Some(response) = answer(&buf[..len], &records) {
+                    let _ = socket.send_to(&response, peer);
+                }
+            }
+        });
+        DnsStub { addr }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+fn answer(query: &[u8], records: &HashMap<String, Ipv4Addr>) -> Option<Vec<u8>> {
+    // 头部12字节，之后是一个问题：名称的标签序列、类型、类
+    let mut pos = 12;
+    let mut labels: Vec<String> = Vec::new();
+    loop {
+        let len = *query.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        labels.push(String::from_utf8_lossy(query.get(pos..pos + len)?).to_ascii_lowercase());
+        pos += len;
+    }
+    let qtype = u16::from_be_bytes([*query.get(pos)?, *query.get(pos + 1)?]);
+    let question = &query[12..pos + 4];
+    let ip = records.get(&labels.join(".")).filter(|_| qtype == 1);
+
+    let mut response = Vec::new();
+    response.extend_from_slice(&query[..2]); // ID
+    response.extend_from_slice(&[0x81, 0x80]); // 响应、期望递归、可以递归、NOERROR
+    response.extend_from_slice(&[0, 1, 0, ip.is_some() as u8, 0, 0, 0, 0]);
+    response.extend_from_slice(question);
+    if let Some(ip) = ip {
+        // 名称指向问题中的名称(偏移12)，A记录，IN类，TTL 60
+        response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+        response.extend_from_slice(&ip.octets());
+    }
+    Some(response)
+}
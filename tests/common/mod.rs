@@ -0,0 +1,165 @@
+// 进程内的假CloudFlare边缘节点：本地HTTP服务器，按请求的Host返回CF-RAY响应头和trace响应体，
+// 可以设置每个地址的数据中心、延迟和失败方式，整个扫描流程不需要访问外网
+#![allow(dead_code)]
+
+pub mod dns;
+
+use curl_cdn_cgi_trace_rust::utils::timeout::TimeoutPolicy;
+use curl_cdn_cgi_trace_rust::Airport;
+
+use std::{
+    collections::HashMap,
+    io::{ BufRead, BufReader, Write },
+    net::{ SocketAddr, TcpListener, TcpStream },
+    sync::{ atomic::{ AtomicUsize, Ordering }, Arc, Mutex },
+    thread,
+    time::Duration,
+};
+
+// 测试使用的超时：连接1秒，整个请求1秒，不重试
+pub fn timeouts() -> TimeoutPolicy {
+    TimeoutPolicy::new(Duration::from_secs(1), Duration::from_secs(1), 0, false)
+}
+
+// 一个地址的响应方式
+#[derive(Clone, Debug)]
+pub enum Behavior {
+    Cloudflare(&'static str), // 正常的CloudFlare响应，参数是数据中心的代码
+    WrongHost(&'static str), // CloudFlare响应，但是trace中的h=不是请求的Host
+    Origin(u16), // 没有CDN的源站，返回这个状态码
+    Drop, // 读取请求后直接关闭连接
+}
+
+#[derive(Clone, Debug)]
+pub struct Edge {
+    pub behavior: Behavior,
+    pub latency: Duration, // 响应前等待的时长
+}
+
+impl Edge {
+    pub fn new(behavior: Behavior) -> Self {
+        Edge { behavior, latency: Duration::ZERO }
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+}
+
+pub struct FakeEdge {
+    addr: SocketAddr,
+    edges: Arc<Mutex<HashMap<String, Edge>>>,
+    requests: Arc<AtomicUsize>,
+}
+
+impl FakeEdge {
+    // 在随机端口启动，没有设置的Host返回404
+    pub fn start(edges: Vec<(&str, Edge)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind fake edge");
+        let addr = listener.local_addr().unwrap();
+        let edges: Arc<Mutex<HashMap<String, Edge>>> = Arc::new(
+            Mutex::new(
+                edges
+                    .into_iter()
+                    .map(|(host, edge)| (host.to_string(), edge))
+                    .collect()
+            )
+        );
+        let shared = Arc::clone(&edges);
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let edges = Arc::clone(&shared);
+                let counter = Arc::clone(&counter);
+                thread::spawn(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let _ = handle(stream, &edges);
+                });
+            }
+        });
+        FakeEdge { addr, edges, requests }
+    }
+
+    // 启动后再添加地址，Host中需要带上服务器的端口时使用
+    pub fn add(&self, host: &str, edge: Edge) {
+        self.edges.lock().unwrap().insert(host.to_string(), edge);
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // 收到的连接数量
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+fn handle(stream: TcpStream, edges: &Mutex<HashMap<String, Edge>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let method = request_line.split(' ').next().unwrap_or_default().to_string();
+    let mut host = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("host") {
+                host = value.trim().to_string();
+            }
+        }
+    }
+    let mut stream = reader.into_inner();
+    let edge = edges
+        .lock()
+        .unwrap()
+        .get(&host)
+        .cloned()
+        .unwrap_or_else(|| Edge::new(Behavior::Origin(404)));
+    thread::sleep(edge.latency);
+
+    let (status, ray, trace_host) = match edge.behavior {
+        Behavior::Cloudflare(colo) => (200, Some(colo), host.clone()),
+        Behavior::WrongHost(colo) => (200, Some(colo), "example.org".to_string()),
+        Behavior::Origin(status) => (status, None, host.clone()),
+        Behavior::Drop => {
+            return Ok(());
+        }
+    };
+    let body = match ray {
+        Some(colo) => format!("fl=1f1\nh={}\nip=127.0.0.1\nts=0\nvisit_scheme=http\ncolo={}\nhttp=http/1.1\n", trace_host, colo),
+        None => "not found\n".to_string(),
+    };
+    let mut response = format!("HTTP/1.1 {} OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n", status, body.len());
+    if let Some(colo) = ray {
+        response.push_str(&format!("Server: cloudflare\r\nCF-RAY: 8f00000000000000-{}\r\n", colo));
+    }
+    response.push_str("Connection: close\r\n\r\n");
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+pub fn airports() -> Vec<Airport> {
+    let airport = |iata: &str, cca2: &str, city: &str| Airport {
+        iata: iata.to_string(),
+        lat: 0.0,
+        lon: 0.0,
+        cca2: cca2.to_string(),
+        region: "North America".to_string(),
+        city: city.to_string(),
+    };
+    vec![airport("SJC", "US", "San Jose"), airport("LAX", "US", "Los Angeles")]
+}
+
+// 本机是否安装了curl，没有安装就跳过curl后端的测试
+pub fn curl_installed() -> bool {
+    std::process::Command::new("curl").arg("--version").output().is_ok()
+}
@@ -0,0 +1,115 @@
+use curl_cdn_cgi_trace_rust::utils::config::{ load_values, splice_config_args };
+
+use clap::{ Arg, ArgAction, ArgMatches, Command };
+use std::{ fs, path::PathBuf };
+use toml::Table;
+
+// 与程序的参数结构相同的简化命令：扫描参数在根命令和jetbrains子命令中都有
+fn scan_args(command: Command) -> Command {
+    command
+        .arg(Arg::new("pool").long("pool").default_value("50"))
+        .arg(Arg::new("filter").long("filter").action(ArgAction::Append))
+        .arg(
+            Arg::new("tcp_prefilter")
+                .long("tcp-prefilter")
+                .action(ArgAction::Set)
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("true")
+                .default_value("false")
+                .value_parser(clap::value_parser!(bool))
+        )
+        .arg(
+            Arg::new("append")
+                .long("append")
+                .action(ArgAction::Set)
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("true")
+                .default_value("false")
+                .value_parser(clap::value_parser!(bool))
+        )
+        .arg(Arg::new("merge").long("merge").num_args(0..=1).default_missing_value("newest").conflicts_with("append"))
+}
+
+fn command() -> Command {
+    let mut root = scan_args(Command::new("trace"))
+        .arg(Arg::new("config").long("config").global(true))
+        .arg(Arg::new("profile").long("profile").global(true))
+        .subcommand(scan_args(Command::new("jetbrains")));
+    root.build();
+    root
+}
+
+fn values(toml: &str) -> Table {
+    toml::from_str(toml).unwrap()
+}
+
+// 插入配置文件的值，再解析一次
+fn parse(argv: &[&str], config: &Table) -> (Vec<String>, ArgMatches) {
+    let root = command();
+    let argv: Vec<String> = argv.iter().map(|arg| arg.to_string()).collect();
+    let matches = root.clone().get_matches_from(&argv);
+    let spliced = splice_config_args(&root, &matches, argv, config);
+    let matches = root.get_matches_from(&spliced);
+    (spliced, matches)
+}
+
+fn config_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cdn-trace-{}-{}.toml", name, std::process::id()));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn profile_overrides_top_level_values() {
+    let path = config_file(
+        "profile",
+        "pool = 50\nfilter = [\"status=200\"]\n[profile.fast]\npool = 200\ntcp-prefilter = true\n"
+    );
+    let top = load_values(&path, None).unwrap();
+    assert_eq!(top.get("pool").and_then(|v| v.as_integer()), Some(50));
+    assert!(top.get("profile").is_none());
+    let fast = load_values(&path, Some("fast")).unwrap();
+    assert_eq!(fast.get("pool").and_then(|v| v.as_integer()), Some(200));
+    assert_eq!(fast.get("tcp-prefilter").and_then(|v| v.as_bool()), Some(true));
+    assert!(fast.get("filter").is_some());
+    assert!(load_values(&path, Some("nope")).is_err());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn command_line_overrides_config() {
+    let config = values("pool = 200\nfilter = [\"status=200\", \"colo=SJC\"]\ntcp-prefilter = true\n");
+    let (argv, matches) = parse(&["trace", "--pool", "10"], &config);
+    assert_eq!(argv, ["trace", "--filter=status=200", "--filter=colo=SJC", "--tcp-prefilter", "--pool", "10"]);
+    assert_eq!(matches.get_one::<String>("pool").unwrap(), "10");
+    assert_eq!(matches.get_many::<String>("filter").unwrap().collect::<Vec<_>>(), ["status=200", "colo=SJC"]);
+    assert!(matches.get_flag("tcp_prefilter"));
+    // 配置文件中打开的开关可以在命令行关闭
+    let (_, matches) = parse(&["trace", "--tcp-prefilter=false"], &config);
+    assert!(!matches.get_flag("tcp_prefilter"));
+}
+
+#[test]
+fn config_values_go_after_the_subcommand() {
+    let config = values("pool = 200\nunknown-key = 1\n");
+    let (argv, matches) = parse(&["trace", "--config", "c.toml", "jetbrains", "--filter", "status=200"], &config);
+    assert_eq!(argv, ["trace", "--config", "c.toml", "jetbrains", "--pool=200", "--filter", "status=200"]);
+    let (_, jetbrains) = matches.subcommand().unwrap();
+    assert_eq!(jetbrains.get_one::<String>("pool").unwrap(), "200");
+    // 子命令前面的参数使用默认值
+    assert_eq!(matches.get_one::<String>("pool").unwrap(), "50");
+}
+
+#[test]
+fn conflicting_config_values_are_skipped() {
+    let config = values("merge = \"best\"\n");
+    let (_, matches) = parse(&["trace"], &config);
+    assert_eq!(matches.get_one::<String>("merge").unwrap(), "best");
+    // 命令行的--append优先于配置文件中的merge
+    let (argv, matches) = parse(&["trace", "--append"], &config);
+    assert_eq!(argv, ["trace", "--append"]);
+    assert!(matches.get_flag("append"));
+    assert!(matches.get_one::<String>("merge").is_none());
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::dns::DnsStub;
+use common::{ Behavior, Edge, FakeEdge };
+use curl_cdn_cgi_trace_rust::utils::dns::{ extract_domain, parse_nameserver, resolve_targets };
+use curl_cdn_cgi_trace_rust::utils::prober::NativeProber;
+use curl_cdn_cgi_trace_rust::Scanner;
+
+use std::net::Ipv4Addr;
+
+#[test]
+fn ports_are_not_part_of_the_domain() {
+    assert_eq!(extract_domain("127.0.0.1:8765"), None);
+    assert_eq!(extract_domain("[2001:db8::1]:8080"), None);
+    assert_eq!(extract_domain("2001:db8::1"), None);
+    assert_eq!(extract_domain("example.com:8080").as_deref(), Some("example.com"));
+    assert_eq!(extract_domain("http://example.com:8080/").as_deref(), Some("example.com"));
+}
+
+#[tokio::test]
+async fn domains_resolve_through_the_given_nameserver() {
+    let stub = DnsStub::start(vec![("edge.test", Ipv4Addr::new(10, 9, 8, 7))]);
+    // 与命令行的--dns 127.0.0.1:<端口>相同
+    let nameserver = parse_nameserver(&stub.addr().to_string()).unwrap();
+    let addrs = vec!["edge.test:8080".to_string(), "127.0.0.1:8765".to_string(), "missing.test".to_string()];
+    let targets = resolve_targets(addrs, Some(nameserver), 4).await;
+
+    // IP:端口不做解析，解析不到的域名被跳过
+    assert_eq!(targets.len(), 2);
+    assert_eq!(targets[0].addr, "edge.test:8080");
+    assert_eq!(targets[0].host.as_deref(), Some("edge.test"));
+    assert_eq!(targets[0].resolved_ip.as_deref(), Some("10.9.8.7"));
+    assert_eq!(targets[1].addr, "127.0.0.1:8765");
+    assert_eq!(targets[1].resolved_ip, None);
+}
+
+#[tokio::test]
+async fn resolved_targets_keep_the_input_order() {
+    let names: Vec<String> = (1..=20).map(|i| format!("edge{}.test", i)).collect();
+    let stub = DnsStub::start(
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), Ipv4Addr::new(10, 9, 0, i as u8)))
+            .collect()
+    );
+    // 域名和IP交错，解析完成的先后不影响顺序
+    let mut addrs: Vec<String> = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        addrs.push(name.clone());
+        addrs.push(format!("127.0.0.{}", i + 1));
+    }
+    for concurrency in [1, 3, 64] {
+        let targets = resolve_targets(addrs.clone(), Some(stub.addr()), concurrency).await;
+        let order: Vec<&str> = targets.iter().map(|target| target.addr.as_str()).collect();
+        assert_eq!(order, addrs);
+    }
+}
+
+#[tokio::test]
+async fn resolved_targets_keep_their_port() {
+    // 不使用connect_to，按解析出来的IP和原地址的端口连接假的边缘节点
+    let edge = FakeEdge::start(vec![]);
+    let port = edge.addr().port();
+    let host = format!("edge.test:{}", port);
+    let plain = format!("127.0.0.1:{}", port);
+    edge.add(&host, Edge::new(Behavior::Cloudflare("SJC")));
+    edge.add(&plain, Edge::new(Behavior::Cloudflare("LAX")));
+    let stub = DnsStub::start(vec![("edge.test", Ipv4Addr::LOCALHOST)]);
+    let targets = resolve_targets(vec![host.clone(), plain.clone()], Some(stub.addr()), 4).await;
+
+    let scan = Scanner::new(targets)
+        .prober(Box::new(NativeProber::new()))
+        .timeouts(common::timeouts())
+        .airports(common::airports())
+        .scan()
+        .unwrap();
+    let mut records: Vec<_> = scan.filter_map(|(_, result)| result.ok()).collect();
+    records.sort_by(|a, b| a.ip.cmp(&b.ip));
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].ip, plain);
+    assert_eq!(records[0].colo, "LAX");
+    assert_eq!(records[1].ip, host);
+    assert_eq!(records[1].resolved_ip, "127.0.0.1");
+    assert_eq!(records[1].colo, "SJC");
+}
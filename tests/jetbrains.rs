@@ -0,0 +1,153 @@
+// 假的JetBrains许可证服务器：按请求的salt返回obtainTicket/ping响应，签名方式可以设置，
+// 用来测试握手和签名校验，证书都是测试中临时生成的
+mod common;
+
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use curl_cdn_cgi_trace_rust::utils::jetbrains::handshake;
+use curl_cdn_cgi_trace_rust::utils::prober::NativeProber;
+use openssl::{
+    asn1::Asn1Time,
+    bn::BigNum,
+    hash::MessageDigest,
+    pkey::{ PKey, Private },
+    rsa::Rsa,
+    sign::Signer,
+    x509::{ extension::BasicConstraints, store::{ X509Store, X509StoreBuilder }, X509NameBuilder, X509 },
+};
+use std::{
+    io::{ BufRead, BufReader, Write },
+    net::{ SocketAddr, TcpListener },
+    sync::Arc,
+    thread,
+};
+
+// 生成证书：issuer为None时是自签名的根证书
+fn certificate(name: &str, issuer: Option<(&X509, &PKey<Private>)>) -> (X509, PKey<Private>) {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let mut subject = X509NameBuilder::new().unwrap();
+    subject.append_entry_by_text("CN", name).unwrap();
+    let subject = subject.build();
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+    builder.set_subject_name(&subject).unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+    builder.set_not_after(&Asn1Time::days_from_now(30).unwrap()).unwrap();
+    match issuer {
+        Some((ca, ca_key)) => {
+            builder.set_issuer_name(ca.subject_name()).unwrap();
+            builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+        }
+        None => {
+            builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap();
+            builder.set_issuer_name(&subject).unwrap();
+            builder.sign(&key, MessageDigest::sha256()).unwrap();
+        }
+    }
+    (builder.build(), key)
+}
+
+fn store(ca: &X509) -> X509Store {
+    let mut builder = X509StoreBuilder::new().unwrap();
+    builder.add_cert(ca.clone()).unwrap();
+    builder.build()
+}
+
+// 响应的签名方式
+enum Signing {
+    Certificate(X509, PKey<Private>), // 算法-签名-证书
+    Legacy, // 只有十六进制的签名，没有证书
+}
+
+fn sign(signing: &Signing, xml: &str) -> String {
+    match signing {
+        Signing::Certificate(cert, key) => {
+            let mut signer = Signer::new(MessageDigest::sha1(), key).unwrap();
+            signer.update(xml.as_bytes()).unwrap();
+            let hex: String = signer
+                .sign_to_vec()
+                .unwrap()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            format!("SHA1withRSA-{}-{}", hex, STANDARD.encode(cert.to_der().unwrap()))
+        }
+        Signing::Legacy => "0123456789abcdef".to_string(),
+    }
+}
+
+fn start(signing: Signing) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let signing = Arc::new(signing);
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let _ = reader.read_line(&mut request_line);
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+            }
+            let path = request_line.split(' ').nth(1).unwrap_or_default();
+            let salt = path
+                .split(['?', '&'])
+                .find_map(|pair| pair.strip_prefix("salt="))
+                .unwrap_or_default();
+            let xml = if path.starts_with("/rpc/obtainTicket.action") {
+                format!(
+                    "<ObtainTicketResponse><message></message><prolongationPeriod>600000</prolongationPeriod><responseCode>OK</responseCode><salt>{}</salt><ticketId>1</ticketId><ticketProperties>licensee=Tester\tlicenseType=0\t</ticketProperties></ObtainTicketResponse>",
+                    salt
+                )
+            } else {
+                format!("<PingResponse><message></message><responseCode>OK</responseCode><salt>{}</salt></PingResponse>", salt)
+            };
+            let body = format!("<!-- {} -->\n{}", sign(&signing, &xml), xml);
+            let _ = stream.write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                ).as_bytes()
+            );
+        }
+    });
+    addr
+}
+
+fn check(addr: SocketAddr, trusted: Option<&X509Store>) -> (bool, bool) {
+    let (server, status) = handshake(
+        &format!("http://{}", addr),
+        &NativeProber::new(),
+        &common::timeouts(),
+        trusted
+    ).unwrap();
+    assert_eq!(status, "200");
+    assert_eq!(server.licensee, "Tester");
+    (server.salt_echoed, server.verified)
+}
+
+#[test]
+fn signature_from_a_trusted_certificate_is_verified() {
+    let (ca, ca_key) = certificate("Test License Servers CA", None);
+    let (leaf, leaf_key) = certificate("license.example.com", Some((&ca, &ca_key)));
+    let addr = start(Signing::Certificate(leaf, leaf_key));
+    assert_eq!(check(addr, Some(&store(&ca))), (true, true));
+    // 没有根证书时只能确认返回了salt
+    assert_eq!(check(addr, None), (true, false));
+}
+
+#[test]
+fn echoing_the_salt_is_not_enough() {
+    let (ca, _) = certificate("Test License Servers CA", None);
+    // 自签名的证书，不是信任的根证书签发的
+    let (fake, fake_key) = certificate("license.example.com", None);
+    let untrusted = start(Signing::Certificate(fake, fake_key));
+    assert_eq!(check(untrusted, Some(&store(&ca))), (true, false));
+    // 旧格式的签名没有证书
+    let legacy = start(Signing::Legacy);
+    assert_eq!(check(legacy, Some(&store(&ca))), (true, false));
+}
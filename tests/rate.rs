@@ -0,0 +1,59 @@
+use curl_cdn_cgi_trace_rust::utils::checkpoint::StopHandle;
+use curl_cdn_cgi_trace_rust::utils::rate::RateLimiter;
+use curl_cdn_cgi_trace_rust::Target;
+
+use std::{ thread, time::{ Duration, Instant } };
+
+fn target(addr: &str) -> Target {
+    Target::from(addr.to_string())
+}
+
+// 等待n个地址的总耗时
+fn elapsed(limiter: &RateLimiter, addrs: &[&str]) -> Duration {
+    let stop = StopHandle::new();
+    let start = Instant::now();
+    for addr in addrs {
+        assert!(limiter.wait(&target(addr), &stop));
+    }
+    start.elapsed()
+}
+
+#[test]
+fn global_rate_allows_one_second_burst() {
+    let limiter = RateLimiter::new(Some(10.0), None);
+    // 前10个是突发，不用等待
+    assert!(elapsed(&limiter, &["10.0.0.1"; 10]) < Duration::from_millis(50));
+    // 之后每个令牌间隔100毫秒
+    let waited = elapsed(&limiter, &["10.0.0.1"; 3]);
+    assert!(waited >= Duration::from_millis(250), "{:?}", waited);
+    assert!(waited < Duration::from_millis(600), "{:?}", waited);
+}
+
+#[test]
+fn per_prefix_rate_only_limits_the_same_prefix() {
+    let limiter = RateLimiter::new(None, Some(5.0));
+    // 不同的/24各自有令牌
+    assert!(elapsed(&limiter, &["10.0.1.1", "10.0.2.1", "10.0.3.1"]) < Duration::from_millis(50));
+    // 同一个/24的第二个地址等待200毫秒
+    let waited = elapsed(&limiter, &["10.0.4.1", "10.0.4.200"]);
+    assert!(waited >= Duration::from_millis(150), "{:?}", waited);
+    assert!(waited < Duration::from_millis(500), "{:?}", waited);
+}
+
+#[test]
+fn stopping_ends_the_wait() {
+    let limiter = RateLimiter::new(Some(0.2), None);
+    let stop = StopHandle::new();
+    assert!(limiter.wait(&target("10.0.5.1"), &stop));
+    // 下一个令牌要5秒后才有，停止后应该马上返回
+    let handle = stop.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        handle.stop();
+    });
+    let start = Instant::now();
+    assert!(!limiter.wait(&target("10.0.5.2"), &stop));
+    assert!(start.elapsed() < Duration::from_secs(1));
+    // 已经停止的不再等待
+    assert!(!limiter.wait(&target("10.0.5.3"), &stop));
+}
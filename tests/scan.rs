@@ -0,0 +1,345 @@
+mod common;
+
+use common::{ Behavior, Edge, FakeEdge };
+use curl_cdn_cgi_trace_rust::utils::files::{ read_records, CsvSink, WriteMode };
+use curl_cdn_cgi_trace_rust::utils::prober::{ CurlProber, NativeProber, Prober };
+use curl_cdn_cgi_trace_rust::{ utils, Record, Scanner, Target };
+
+use std::time::Duration;
+
+fn targets(addrs: &[&str]) -> Vec<Target> {
+    addrs
+        .iter()
+        .map(|addr| Target::from(addr.to_string()))
+        .collect()
+}
+
+// 扫描全部目标，返回按地址排序的成功结果和错误数量
+fn run(edge: &FakeEdge, prober: Box<dyn Prober>, targets: Vec<Target>) -> (Vec<Record>, usize) {
+    let mut scan = Scanner::new(targets)
+        .pool(8)
+        .prober(prober)
+        .timeouts(common::timeouts())
+        .airports(common::airports())
+        .scan()
+        .unwrap();
+    let mut records = Vec::new();
+    let mut errors = 0;
+    for (_, result) in scan.by_ref() {
+        match result {
+            Ok(record) => records.push(record),
+            Err(_) => {
+                errors += 1;
+            }
+        }
+    }
+    let finished = scan.finish().unwrap();
+    assert!(finished.remaining.is_empty());
+    assert!(edge.requests() > 0);
+    records.sort_by(|a, b| a.ip.cmp(&b.ip));
+    (records, errors)
+}
+
+fn native(edge: &FakeEdge) -> Box<dyn Prober> {
+    Box::new(NativeProber::new().connect_to(edge.addr()))
+}
+
+#[test]
+fn cdn_scan_reports_colo_and_location() {
+    let edge = FakeEdge::start(
+        vec![
+            ("10.0.0.1", Edge::new(Behavior::Cloudflare("SJC"))),
+            ("10.0.0.2", Edge::new(Behavior::Cloudflare("LAX")))
+        ]
+    );
+    let (records, errors) = run(&edge, native(&edge), targets(&["10.0.0.1", "10.0.0.2"]));
+    assert_eq!(errors, 0);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].ip, "10.0.0.1");
+    assert_eq!(records[0].colo, "SJC");
+    assert_eq!(records[0].city, "San Jose");
+    assert_eq!(records[0].country, "US");
+    assert_eq!(records[1].colo, "LAX");
+    for record in &records {
+        assert_eq!(record.cdn, "cloudflare");
+        assert!(record.detection.contains("cf-ray"));
+        assert_eq!(record.http_status_code, "200");
+        assert!(!record.scanned_at.is_empty());
+    }
+}
+
+#[test]
+fn failures_are_reported_as_errors() {
+    let edge = FakeEdge::start(
+        vec![
+            ("10.0.1.1", Edge::new(Behavior::Cloudflare("SJC"))),
+            ("10.0.1.2", Edge::new(Behavior::Origin(503))),
+            ("10.0.1.3", Edge::new(Behavior::Drop)),
+            // 超过1秒的总超时
+            ("10.0.1.4", Edge::new(Behavior::Cloudflare("SJC")).latency(Duration::from_secs(3)))
+        ]
+    );
+    let (records, errors) = run(
+        &edge,
+        native(&edge),
+        targets(&["10.0.1.1", "10.0.1.2", "10.0.1.3", "10.0.1.4"])
+    );
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].ip, "10.0.1.1");
+    assert_eq!(errors, 3);
+}
+
+#[test]
+fn latency_is_measured() {
+    let edge = FakeEdge::start(
+        vec![("10.0.2.1", Edge::new(Behavior::Cloudflare("SJC")).latency(Duration::from_millis(300)))]
+    );
+    let (records, _) = run(&edge, native(&edge), targets(&["10.0.2.1"]));
+    assert_eq!(records.len(), 1);
+    assert!(records[0].delay >= Duration::from_millis(300));
+    assert!(records[0].delay < Duration::from_secs(1));
+}
+
+#[test]
+fn host_verification_uses_trace_host() {
+    let edge = FakeEdge::start(
+        vec![
+            ("good.example.com", Edge::new(Behavior::Cloudflare("SJC"))),
+            ("bad.example.com", Edge::new(Behavior::WrongHost("SJC")))
+        ]
+    );
+    let pinned = |host: &str| Target {
+        addr: "10.0.3.1".to_string(),
+        host: Some(host.to_string()),
+        resolved_ip: Some("10.0.3.1".to_string()),
+        verify_host: true,
+    };
+    let (records, errors) = run(
+        &edge,
+        native(&edge),
+        vec![pinned("good.example.com"), pinned("bad.example.com")]
+    );
+    assert_eq!(errors, 1);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].domain, "good.example.com");
+    assert_eq!(records[0].resolved_ip, "10.0.3.1");
+}
+
+#[test]
+fn filtered_records_are_written_to_sinks() {
+    let edge = FakeEdge::start(
+        vec![
+            ("10.0.4.1", Edge::new(Behavior::Cloudflare("SJC"))),
+            ("10.0.4.2", Edge::new(Behavior::Cloudflare("LAX"))),
+            ("10.0.4.3", Edge::new(Behavior::Cloudflare("SJC")))
+        ]
+    );
+    let path = std::env::temp_dir().join(format!("cdn-trace-scan-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let columns = utils::columns::default_columns(utils::models::ScanMode::Cdn);
+    let sort_by = utils::columns::default_sort(utils::models::ScanMode::Cdn);
+    let mut scan = Scanner::new(targets(&["10.0.4.1", "10.0.4.2", "10.0.4.3"]))
+        .prober(native(&edge))
+        .timeouts(common::timeouts())
+        .airports(common::airports())
+        .filter("colo=SJC".parse().unwrap())
+        .sink(Box::new(CsvSink::new(&path, columns, sort_by, WriteMode::Overwrite)))
+        .scan()
+        .unwrap();
+    // 迭代得到全部结果，过滤条件只影响写入输出的结果
+    assert_eq!(scan.by_ref().filter(|(_, result)| result.is_ok()).count(), 3);
+    scan.finish().unwrap();
+
+    let mut written = read_records(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    written.sort_by(|a, b| a.ip.cmp(&b.ip));
+    let ips: Vec<&str> = written.iter().map(|record| record.ip.as_str()).collect();
+    assert_eq!(ips, ["10.0.4.1", "10.0.4.3"]);
+    assert!(written.iter().all(|record| record.colo == "SJC"));
+}
+
+#[test]
+fn curl_prober_matches_native_prober() {
+    if !common::curl_installed() {
+        return;
+    }
+    let edge = FakeEdge::start(
+        vec![
+            ("10.0.5.1", Edge::new(Behavior::Cloudflare("SJC"))),
+            ("10.0.5.2", Edge::new(Behavior::Origin(403)))
+        ]
+    );
+    let addrs = ["10.0.5.1", "10.0.5.2"];
+    let (native_records, native_errors) = run(&edge, native(&edge), targets(&addrs));
+    let curl = Box::new(CurlProber::new().connect_to(edge.addr()));
+    let (curl_records, curl_errors) = run(&edge, curl, targets(&addrs));
+    assert_eq!(native_errors, 1);
+    assert_eq!(curl_errors, 1);
+    assert_eq!(curl_records.len(), 1);
+    assert_eq!(curl_records[0].ip, native_records[0].ip);
+    assert_eq!(curl_records[0].colo, native_records[0].colo);
+    assert_eq!(curl_records[0].detection, native_records[0].detection);
+}
+
+#[test]
+fn csv_append_keeps_existing_rows() {
+    use curl_cdn_cgi_trace_rust::utils::columns::Column;
+    use curl_cdn_cgi_trace_rust::utils::files::ResultSink;
+
+    let path = std::env::temp_dir().join(format!("cdn-trace-append-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    // 原文件有一列本次不输出的"备注"，追加时不能丢掉
+    std::fs::write(&path, "IP地址,数据中心,备注\n10.0.6.1,SJC,keep\n").unwrap();
+    let record = Record { ip: "10.0.6.2".to_string(), colo: "LAX".to_string(), ..Record::default() };
+    let append = |columns: Vec<Column>| -> Result<(), Box<dyn std::error::Error>> {
+        let mut sink = CsvSink::new(&path, columns, Vec::new(), WriteMode::Append);
+        sink.open()?;
+        sink.write_record(&record)?;
+        sink.finish()
+    };
+    assert!(append(vec![Column::Ip, Column::Colo]).is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "IP地址,数据中心,备注\n10.0.6.1,SJC,keep\n");
+
+    std::fs::write(&path, "ip,colo\n10.0.6.1,SJC").unwrap();
+    append(vec![Column::Ip, Column::Colo]).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(text, "ip,colo\n10.0.6.1,SJC\n10.0.6.2,LAX\n");
+}
+
+#[test]
+fn csv_append_adds_the_missing_scanned_at_column() {
+    use curl_cdn_cgi_trace_rust::utils::columns::Column;
+    use curl_cdn_cgi_trace_rust::utils::files::{ create_sink, OutputFormat };
+
+    let path = std::env::temp_dir().join(format!("cdn-trace-append-default-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    // 没有--append时输出的文件没有扫描时间列
+    std::fs::write(&path, "ip,colo\n10.0.8.1,SJC\n").unwrap();
+    let record = Record {
+        ip: "10.0.8.2".to_string(),
+        colo: "LAX".to_string(),
+        scanned_at: "2024-01-01 00:00:00".to_string(),
+        ..Record::default()
+    };
+    let mut sink = create_sink(OutputFormat::Csv, &path, vec![Column::Ip, Column::Colo], Vec::new(), WriteMode::Append);
+    sink.open().unwrap();
+    sink.write_record(&record).unwrap();
+    sink.finish().unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("ip,colo,"), "{}", lines[0]);
+    assert_eq!(lines[1], "10.0.8.1,SJC,");
+    assert_eq!(lines[2], "10.0.8.2,LAX,2024-01-01 00:00:00");
+}
+
+#[test]
+fn csv_merge_keeps_existing_columns() {
+    use curl_cdn_cgi_trace_rust::utils::columns::Column;
+    use curl_cdn_cgi_trace_rust::utils::files::{ create_sink, MergeKeep, OutputFormat };
+
+    let path = std::env::temp_dir().join(format!("cdn-trace-merge-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let merge = |columns: Vec<Column>| -> Result<(), Box<dyn std::error::Error>> {
+        let record = Record { ip: "10.0.9.2".to_string(), colo: "LAX".to_string(), ..Record::default() };
+        let mut sink = create_sink(OutputFormat::Csv, &path, columns, Vec::new(), WriteMode::Merge(MergeKeep::Newest));
+        sink.open()?;
+        sink.write_record(&record)?;
+        sink.finish()
+    };
+    // 不认识的列不能保留，拒绝合并
+    std::fs::write(&path, "ip,colo,备注\n10.0.9.1,SJC,keep\n").unwrap();
+    assert!(merge(vec![Column::Ip, Column::Colo]).is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "ip,colo,备注\n10.0.9.1,SJC,keep\n");
+    // 本次不输出的city列保留下来
+    std::fs::write(&path, "ip,colo,city\n10.0.9.1,SJC,San Jose\n").unwrap();
+    merge(vec![Column::Ip, Column::Colo]).unwrap();
+    let records = read_records(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(records.len(), 2);
+    let existing = records.iter().find(|record| record.ip == "10.0.9.1").unwrap();
+    assert_eq!(existing.city, "San Jose");
+}
+
+#[test]
+fn stopped_scan_returns_remaining_targets() {
+    let edge = FakeEdge::start(
+        vec![
+            ("10.0.7.1", Edge::new(Behavior::Cloudflare("SJC")).latency(Duration::from_millis(300))),
+            ("10.0.7.2", Edge::new(Behavior::Cloudflare("SJC"))),
+            ("10.0.7.3", Edge::new(Behavior::Cloudflare("SJC")))
+        ]
+    );
+    let mut scan = Scanner::new(targets(&["10.0.7.1", "10.0.7.2", "10.0.7.3"]))
+        .pool(1)
+        .prober(native(&edge))
+        .timeouts(common::timeouts())
+        .scan()
+        .unwrap();
+    // 第一个目标扫描中停止，后面的目标不再扫描
+    std::thread::sleep(Duration::from_millis(100));
+    scan.stop_handle().stop();
+    assert_eq!(scan.by_ref().count(), 1);
+    let finished = scan.finish().unwrap();
+    assert_eq!(finished.completed.len(), 1);
+    assert_eq!(finished.remaining.len(), 2);
+}
+
+#[test]
+fn resumed_scan_appends_to_the_interrupted_csv() {
+    use curl_cdn_cgi_trace_rust::utils::files::{ create_sink, OutputFormat };
+
+    let edge = FakeEdge::start(
+        vec![
+            ("10.0.8.1", Edge::new(Behavior::Cloudflare("SJC")).latency(Duration::from_millis(300))),
+            ("10.0.8.2", Edge::new(Behavior::Cloudflare("LAX"))),
+            ("10.0.8.3", Edge::new(Behavior::Cloudflare("SJC")))
+        ]
+    );
+    let path = std::env::temp_dir().join(format!("cdn-trace-resume-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let mode = utils::models::ScanMode::Cdn;
+    let sink = |write_mode| {
+        create_sink(
+            OutputFormat::Csv,
+            &path,
+            utils::columns::default_columns(mode),
+            utils::columns::default_sort(mode),
+            write_mode
+        )
+    };
+
+    // 第一次扫描：第一个目标扫描中停止，和没有指定--append时一样覆盖输出文件
+    let mut scan = Scanner::new(targets(&["10.0.8.1", "10.0.8.2", "10.0.8.3"]))
+        .pool(1)
+        .prober(native(&edge))
+        .timeouts(common::timeouts())
+        .sink(sink(WriteMode::Overwrite))
+        .scan()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    scan.stop_handle().stop();
+    scan.by_ref().for_each(drop);
+    let finished = scan.finish().unwrap();
+    assert_eq!(finished.remaining.len(), 2);
+
+    // 继续扫描剩下的目标，写入同一个文件
+    let mut scan = Scanner::new(finished.remaining)
+        .prober(native(&edge))
+        .timeouts(common::timeouts())
+        .sink(sink(WriteMode::Resume))
+        .scan()
+        .unwrap();
+    scan.by_ref().for_each(drop);
+    scan.finish().unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let mut written = read_records(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(text.lines().count(), 4);
+    written.sort_by(|a, b| a.ip.cmp(&b.ip));
+    let ips: Vec<&str> = written.iter().map(|record| record.ip.as_str()).collect();
+    assert_eq!(ips, ["10.0.8.1", "10.0.8.2", "10.0.8.3"]);
+}
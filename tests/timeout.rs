@@ -0,0 +1,88 @@
+use curl_cdn_cgi_trace_rust::utils::checkpoint::StopHandle;
+use curl_cdn_cgi_trace_rust::utils::timeout::{ parse_retries, TimeoutPolicy };
+
+use std::{ thread, time::{ Duration, Instant } };
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+fn adaptive() -> TimeoutPolicy {
+    TimeoutPolicy::new(Duration::from_secs(3), Duration::from_secs(10), 0, true)
+}
+
+#[test]
+fn adaptive_timeout_waits_for_enough_samples() {
+    let policy = adaptive();
+    for _ in 0..19 {
+        policy.record(ms(100));
+    }
+    assert_eq!(policy.current(), (Duration::from_secs(3), Duration::from_secs(10)));
+    policy.record(ms(100));
+    // 100毫秒的3倍低于下限，使用500毫秒
+    assert_eq!(policy.current(), (ms(500), ms(500)));
+}
+
+#[test]
+fn adaptive_timeout_uses_p90() {
+    let policy = adaptive();
+    // 90个快的样本和10个800毫秒的样本，p90是800毫秒，超时收紧到它的3倍
+    for i in 1..=90 {
+        policy.record(ms(i));
+    }
+    for _ in 0..10 {
+        policy.record(ms(800));
+    }
+    assert_eq!(policy.current(), (ms(2400), ms(2400)));
+    // 不会超过配置的连接超时
+    for _ in 0..100 {
+        policy.record(ms(2000));
+    }
+    assert_eq!(policy.current(), (Duration::from_secs(3), ms(6000)));
+}
+
+#[test]
+fn fixed_timeout_ignores_samples() {
+    let policy = TimeoutPolicy::new(Duration::from_secs(3), Duration::from_secs(10), 0, false);
+    for _ in 0..100 {
+        policy.record(ms(10));
+    }
+    assert_eq!(policy.current(), (Duration::from_secs(3), Duration::from_secs(10)));
+}
+
+#[test]
+fn backoff_doubles_up_to_total() {
+    let policy = TimeoutPolicy::new(Duration::from_secs(3), Duration::from_secs(2), 10, false);
+    assert_eq!(policy.backoff(0), ms(200));
+    assert_eq!(policy.backoff(1), ms(400));
+    assert_eq!(policy.backoff(3), ms(1600));
+    assert_eq!(policy.backoff(4), Duration::from_secs(2));
+    assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(2));
+}
+
+#[test]
+fn retries_are_bounded() {
+    assert_eq!(parse_retries("0"), Ok(0));
+    assert_eq!(parse_retries("10"), Ok(10));
+    assert!(parse_retries("11").is_err());
+    assert!(parse_retries("-1").is_err());
+}
+
+#[test]
+fn stopping_ends_the_retries() {
+    let mut policy = TimeoutPolicy::new(Duration::from_secs(3), Duration::from_secs(10), 10, false);
+    let stop = StopHandle::new();
+    policy.stop_handle(stop.clone());
+    assert!(policy.retry(0));
+    assert!(!policy.retry(10));
+    let stopper = thread::spawn(move || {
+        thread::sleep(ms(100));
+        stop.stop();
+    });
+    // 第5次重试要等待3.2秒，停止后马上返回
+    let start = Instant::now();
+    assert!(!policy.retry(4));
+    assert!(start.elapsed() < Duration::from_secs(1), "{:?}", start.elapsed());
+    stopper.join().unwrap();
+    assert!(!policy.retry(0));
+}